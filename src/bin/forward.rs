@@ -0,0 +1,314 @@
+//! `tmapi-forward`: push new messages of barid inboxes to a webhook.
+//!
+//! ```text
+//! tmapi-forward <config.json>
+//! ```
+//!
+//! The config file looks like this, every field but `addresses` and `webhook_url` is optional:
+//! ```json
+//! {
+//!   "addresses": ["y@iusearch.lol"],
+//!   "webhook_url": "https://example.com/hooks/mail",
+//!   "secret": "shared-secret",
+//!   "attachments": false,
+//!   "poll_interval_secs": 30,
+//!   "max_attempts": 5,
+//!   "state_file": "tmapi-forward.state.json",
+//!   "dead_letter_file": "tmapi-forward.dead.jsonl"
+//! }
+//! ```
+//!
+//! A `poll_interval_secs` of 0 is raised to 1 second.
+//!
+//! Each new message is POSTed as JSON. When a `secret` is set, the body is signed with
+//! HMAC-SHA256 and the signature is sent as `X-Tmapi-Signature: sha256=<hex>`.
+//! Deliveries that still fail after `max_attempts` are appended to the dead-letter file.
+//! Delivered message IDs are kept in the state file, so restarts don't re-deliver.
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tmapi::{Attachment, Client, Email};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const SIGNATURE_HEADER: &str = "X-Tmapi-Signature";
+/// Shortest wait between two polls, smaller `poll_interval_secs` are raised to it.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest wait between two delivery attempts, the wait doubles from 2 seconds up to this.
+const MAX_BACKOFF: Duration = Duration::from_secs(64);
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    addresses: Vec<String>,
+    webhook_url: String,
+    secret: Option<String>,
+    #[serde(default)]
+    attachments: bool,
+    #[serde(default = "default_poll_interval")]
+    poll_interval_secs: u64,
+    #[serde(default = "default_max_attempts")]
+    max_attempts: u32,
+    #[serde(default = "default_state_file")]
+    state_file: PathBuf,
+    #[serde(default = "default_dead_letter_file")]
+    dead_letter_file: PathBuf,
+}
+fn default_poll_interval() -> u64 {
+    30
+}
+fn default_max_attempts() -> u32 {
+    5
+}
+fn default_state_file() -> PathBuf {
+    "tmapi-forward.state.json".into()
+}
+fn default_dead_letter_file() -> PathBuf {
+    "tmapi-forward.dead.jsonl".into()
+}
+
+/// Message IDs already delivered (or dead-lettered), per address.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    delivered: HashMap<String, HashSet<String>>,
+}
+impl State {
+    fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+    /// Forget IDs that are no longer in the inbox, so the state doesn't grow forever.
+    fn retain(&mut self, address: &str, listed: &HashSet<String>) {
+        if let Some(ids) = self.delivered.get_mut(address) {
+            ids.retain(|id| listed.contains(id));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    address: &'a str,
+    email: &'a Email,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attachments: Option<Vec<AttachmentPayload>>,
+}
+#[derive(Debug, Serialize)]
+struct AttachmentPayload {
+    #[serde(flatten)]
+    attachment: Attachment,
+    /// Base64 encoded content
+    content: String,
+}
+impl AttachmentPayload {
+    fn new(attachment: Attachment, content: &[u8]) -> Self {
+        Self {
+            attachment,
+            content: BASE64_STANDARD.encode(content),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeadLetter<'a> {
+    address: &'a str,
+    email_id: &'a str,
+    error: String,
+    payload: &'a serde_json::value::RawValue,
+}
+
+struct Forwarder {
+    config: Config,
+    http: reqwest::Client,
+    state: State,
+}
+impl Forwarder {
+    async fn poll(&mut self, client: &Client) -> Result<(), Error> {
+        let address = client.email.to_string();
+        // Every page, so IDs missing from the listing are really gone.
+        let mut emails = client.get_all_emails().await?;
+        let listed = emails.iter().map(|email| email.id.clone()).collect();
+        self.state.retain(&address, &listed);
+
+        emails.sort_by_key(|email| email.received_at);
+        for email in &emails {
+            let delivered = self.state.delivered.entry(address.clone()).or_default();
+            if delivered.contains(&email.id) {
+                continue;
+            }
+            let attachments = if self.config.attachments && email.has_attachments {
+                Some(fetch_attachments(client, &email.id).await?)
+            } else {
+                None
+            };
+            let payload = Payload {
+                address: &address,
+                email,
+                attachments,
+            };
+            let body = serde_json::to_string(&payload)?;
+            if let Err(error) = self.deliver(&body).await {
+                eprintln!("giving up on {} for {address}: {error}", email.id);
+                self.dead_letter(&address, &email.id, error, &body)?;
+            }
+            self.state
+                .delivered
+                .entry(address.clone())
+                .or_default()
+                .insert(email.id.clone());
+            self.state.save(&self.config.state_file)?;
+        }
+        Ok(())
+    }
+
+    /// POST the body, retrying with exponential backoff.
+    async fn deliver(&self, body: &str) -> Result<(), Error> {
+        let mut attempt = 1;
+        loop {
+            let mut request = self
+                .http
+                .post(&self.config.webhook_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_owned());
+            if let Some(secret) = &self.config.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+            }
+            let error: Error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => format!("webhook answered {}", response.status()).into(),
+                Err(e) => e.into(),
+            };
+            if attempt >= self.config.max_attempts {
+                return Err(error);
+            }
+            eprintln!("delivery attempt {attempt} failed: {error}");
+            tokio::time::sleep(backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    fn dead_letter(
+        &self,
+        address: &str,
+        email_id: &str,
+        error: Error,
+        body: &str,
+    ) -> Result<(), Error> {
+        let payload = serde_json::value::RawValue::from_string(body.to_owned())?;
+        let letter = DeadLetter {
+            address,
+            email_id,
+            error: error.to_string(),
+            payload: &payload,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.dead_letter_file)?;
+        writeln!(file, "{}", serde_json::to_string(&letter)?)?;
+        Ok(())
+    }
+}
+
+async fn fetch_attachments(
+    client: &Client,
+    email_id: &str,
+) -> Result<Vec<AttachmentPayload>, Error> {
+    let mut payloads = vec![];
+    for attachment in client.get_attachments(email_id).await? {
+        let content = client.download_attachment(&attachment.id).await?;
+        payloads.push(AttachmentPayload::new(attachment, &content));
+    }
+    Ok(payloads)
+}
+
+/// How long to wait after failed attempt number `attempt`.
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// `sha256=<hex>` HMAC of the body.
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length works");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: tmapi-forward <config.json>");
+        std::process::exit(2);
+    };
+    let config: Config = serde_json::from_slice(&fs::read(path)?)?;
+    let clients = config
+        .addresses
+        .iter()
+        .map(Client::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    let interval = Duration::from_secs(config.poll_interval_secs).max(MIN_POLL_INTERVAL);
+    let mut forwarder = Forwarder {
+        state: State::load(&config.state_file)?,
+        http: reqwest::Client::new(),
+        config,
+    };
+
+    loop {
+        for client in &clients {
+            if let Err(error) = forwarder.poll(client).await {
+                eprintln!("polling {} failed: {error}", client.email);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature() {
+        // RFC 4231, test case 2
+        let signature = sign("Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(40), MAX_BACKOFF);
+    }
+    #[test]
+    fn state_forgets_removed_messages() {
+        let mut state = State::default();
+        state.delivered.insert(
+            "y@iusearch.lol".into(),
+            HashSet::from(["a".to_owned(), "b".to_owned()]),
+        );
+        state.retain("y@iusearch.lol", &HashSet::from(["b".to_owned()]));
+        assert_eq!(
+            state.delivered["y@iusearch.lol"],
+            HashSet::from(["b".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,955 @@
+//! `tmapi-imap`: a minimal IMAP4rev1 server in front of barid inboxes.
+//!
+//! ```text
+//! tmapi-imap [--listen 127.0.0.1:1143] [--password <password>]
+//! ```
+//!
+//! Log in with a barid address as the user name, its messages show up in `INBOX`.
+//! Any password is accepted unless `--password` is given.
+//! Mailboxes are read-only except for deletion: messages flagged `\Deleted` are removed with
+//! [`Client::delete_inbox`] on `EXPUNGE` or `CLOSE`.
+//!
+//! Supported commands: `CAPABILITY`, `NOOP`, `LOGOUT`, `LOGIN`, `LIST`, `LSUB`, `STATUS`,
+//! `SELECT`, `EXAMINE`, `CHECK`, `CLOSE`, `UNSELECT`, `EXPUNGE`, `SEARCH`, `FETCH`, `STORE`
+//! and their `UID` variants.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use time::OffsetDateTime;
+use tmapi::{Client, Email, EmailAddress};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const CAPABILITIES: &str = "IMAP4rev1 UNSELECT LITERAL+";
+/// Largest literal accepted from a client.
+const MAX_LITERAL: usize = 64 * 1024;
+
+/// State shared by every connection.
+#[derive(Debug)]
+struct Server {
+    password: Option<String>,
+    uid_validity: u32,
+    /// Message ID -> UID, per address. UIDs stay stable while the server runs.
+    uids: Mutex<HashMap<String, UidMap>>,
+}
+#[derive(Debug, Default)]
+struct UidMap {
+    last: u32,
+    by_id: HashMap<String, u32>,
+}
+impl UidMap {
+    fn uid(&mut self, id: &str) -> u32 {
+        *self.by_id.entry(id.to_owned()).or_insert_with(|| {
+            self.last += 1;
+            self.last
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Message {
+    uid: u32,
+    email: Email,
+    seen: bool,
+    deleted: bool,
+    raw: Option<String>,
+}
+impl Message {
+    fn flags(&self) -> String {
+        let mut flags = vec![];
+        if self.seen {
+            flags.push("\\Seen");
+        }
+        if self.deleted {
+            flags.push("\\Deleted");
+        }
+        format!("({})", flags.join(" "))
+    }
+}
+
+#[derive(Debug)]
+struct Mailbox {
+    read_only: bool,
+    messages: Vec<Message>,
+}
+
+/// A parsed client command, literals are collected separately.
+#[derive(Debug)]
+struct Command {
+    tag: String,
+    name: String,
+    args: String,
+    literals: Vec<String>,
+}
+
+/// A message set like `1:4,7,9:*`.
+#[derive(Debug, PartialEq)]
+struct Set(Vec<(u32, u32)>);
+impl Set {
+    fn parse(set: &str, largest: u32) -> Option<Self> {
+        let number = |n: &str| match n {
+            "*" => Some(largest),
+            n => n.parse().ok(),
+        };
+        set.split(',')
+            .map(|range| match range.split_once(':') {
+                Some((start, end)) => {
+                    let (start, end) = (number(start)?, number(end)?);
+                    Some((start.min(end), start.max(end)))
+                }
+                None => number(range).map(|n| (n, n)),
+            })
+            .collect::<Option<_>>()
+            .map(Self)
+    }
+    fn contains(&self, n: u32) -> bool {
+        self.0
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&n))
+    }
+}
+
+struct Session {
+    server: Arc<Server>,
+    client: Option<Client>,
+    mailbox: Option<Mailbox>,
+    reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+    writer: tokio::net::tcp::OwnedWriteHalf,
+}
+
+impl Session {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.write(&format!(
+            "* OK [CAPABILITY {CAPABILITIES}] tmapi IMAP bridge ready\r\n"
+        ))
+        .await?;
+        while let Some(command) = self.read_command().await? {
+            let tag = command.tag.clone();
+            let result = self.handle(command).await;
+            match result {
+                Ok(Reply::Ok(text)) => self.write(&format!("{tag} OK {text}\r\n")).await?,
+                Ok(Reply::No(text)) => self.write(&format!("{tag} NO {text}\r\n")).await?,
+                Ok(Reply::Bad(text)) => self.write(&format!("{tag} BAD {text}\r\n")).await?,
+                Ok(Reply::Logout) => {
+                    self.write("* BYE logging out\r\n").await?;
+                    self.write(&format!("{tag} OK LOGOUT completed\r\n"))
+                        .await?;
+                    return Ok(());
+                }
+                Err(error) => self.write(&format!("{tag} NO {error}\r\n")).await?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<(), Error> {
+        self.writer.write_all(data.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Read one command, answering continuation requests for synchronizing literals.
+    async fn read_command(&mut self) -> Result<Option<Command>, Error> {
+        let mut text = String::new();
+        let mut literals = vec![];
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            match literal_size(line) {
+                Some((size, synchronizing)) => {
+                    if size > MAX_LITERAL {
+                        return Err("literal too large".into());
+                    }
+                    if synchronizing {
+                        self.write("+ Ready\r\n").await?;
+                    }
+                    let mut literal = vec![0; size];
+                    self.reader.read_exact(&mut literal).await?;
+                    let open = line.rfind('{').unwrap();
+                    text.push_str(&line[..open]);
+                    text.push_str("{}");
+                    literals.push(String::from_utf8_lossy(&literal).into_owned());
+                }
+                None => {
+                    text.push_str(line);
+                    break;
+                }
+            }
+        }
+        let mut parts = text.splitn(3, ' ');
+        let tag = parts.next().unwrap_or_default().to_owned();
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let args = parts.next().unwrap_or_default().to_owned();
+        Ok(Some(Command {
+            tag,
+            name,
+            args,
+            literals,
+        }))
+    }
+
+    async fn handle(&mut self, command: Command) -> Result<Reply, Error> {
+        let mut args = Tokens::new(&command.args, command.literals);
+        match (command.name.as_str(), &self.client, &self.mailbox) {
+            ("CAPABILITY", _, _) => {
+                self.write(&format!("* CAPABILITY {CAPABILITIES}\r\n"))
+                    .await?;
+                Ok(Reply::Ok("CAPABILITY completed".into()))
+            }
+            ("NOOP" | "CHECK", _, Some(_)) => {
+                self.refresh().await?;
+                Ok(Reply::Ok(format!("{} completed", command.name)))
+            }
+            ("NOOP", _, _) => Ok(Reply::Ok("NOOP completed".into())),
+            ("LOGOUT", _, _) => Ok(Reply::Logout),
+            ("LOGIN", None, _) => {
+                let (Some(user), Some(password)) = (args.next(), args.next()) else {
+                    return Ok(Reply::Bad("expected user name and password".into()));
+                };
+                if self
+                    .server
+                    .password
+                    .as_ref()
+                    .is_some_and(|p| *p != password)
+                {
+                    return Ok(Reply::No(
+                        "[AUTHENTICATIONFAILED] invalid credentials".into(),
+                    ));
+                }
+                match Client::new(&user) {
+                    Ok(client) => {
+                        self.client = Some(client);
+                        Ok(Reply::Ok("LOGIN completed".into()))
+                    }
+                    Err(error) => Ok(Reply::No(format!("[AUTHENTICATIONFAILED] {error}"))),
+                }
+            }
+            ("LOGIN", Some(_), _) => Ok(Reply::Bad("already logged in".into())),
+            (_, None, _) => Ok(Reply::Bad("log in first".into())),
+            ("LIST" | "LSUB", _, _) => {
+                let _reference = args.next();
+                let pattern = args.next().unwrap_or_default();
+                if pattern.is_empty() {
+                    self.write("* LIST (\\Noselect) \"/\" \"\"\r\n").await?;
+                } else if ["*", "%", "INBOX"].contains(&pattern.to_ascii_uppercase().as_str()) {
+                    self.write(&format!(
+                        "* {} (\\HasNoChildren) \"/\" INBOX\r\n",
+                        command.name
+                    ))
+                    .await?;
+                }
+                Ok(Reply::Ok(format!("{} completed", command.name)))
+            }
+            ("STATUS", _, _) => {
+                if !is_inbox(args.next()) {
+                    return Ok(Reply::No("no such mailbox".into()));
+                }
+                let mailbox = self.load(true).await?;
+                let unseen = mailbox.messages.iter().filter(|m| !m.seen).count();
+                let next = mailbox.messages.last().map_or(1, |m| m.uid + 1);
+                let status = format!(
+                    "* STATUS INBOX (MESSAGES {} RECENT 0 UIDNEXT {next} UIDVALIDITY {} UNSEEN {unseen})\r\n",
+                    mailbox.messages.len(),
+                    self.server.uid_validity
+                );
+                self.write(&status).await?;
+                Ok(Reply::Ok("STATUS completed".into()))
+            }
+            ("SELECT" | "EXAMINE", _, _) => {
+                self.mailbox = None;
+                if !is_inbox(args.next()) {
+                    return Ok(Reply::No("no such mailbox".into()));
+                }
+                let read_only = command.name == "EXAMINE";
+                let mailbox = self.load(read_only).await?;
+                let next = mailbox.messages.last().map_or(1, |m| m.uid + 1);
+                let untagged = format!(
+                    "* {} EXISTS\r\n* 0 RECENT\r\n* FLAGS (\\Seen \\Deleted)\r\n\
+                     * OK [PERMANENTFLAGS (\\Deleted)] limited\r\n\
+                     * OK [UIDVALIDITY {}] UIDs valid\r\n* OK [UIDNEXT {next}] predicted next UID\r\n",
+                    mailbox.messages.len(),
+                    self.server.uid_validity
+                );
+                self.mailbox = Some(mailbox);
+                self.write(&untagged).await?;
+                let access = if read_only { "READ-ONLY" } else { "READ-WRITE" };
+                Ok(Reply::Ok(format!("[{access}] {} completed", command.name)))
+            }
+            (_, _, None) => Ok(Reply::Bad("select a mailbox first".into())),
+            ("CLOSE", _, _) => {
+                self.expunge(true).await?;
+                self.mailbox = None;
+                Ok(Reply::Ok("CLOSE completed".into()))
+            }
+            ("UNSELECT", _, _) => {
+                self.mailbox = None;
+                Ok(Reply::Ok("UNSELECT completed".into()))
+            }
+            ("EXPUNGE", _, _) => self.expunge(false).await,
+            ("SEARCH", _, _) => self.search(&command.args, false).await,
+            ("FETCH", _, _) => self.fetch(&command.args, false).await,
+            ("STORE", _, _) => self.store(&command.args, false).await,
+            ("UID", _, _) => {
+                let (name, rest) = command.args.split_once(' ').unwrap_or((&command.args, ""));
+                match name.to_ascii_uppercase().as_str() {
+                    "SEARCH" => self.search(rest, true).await,
+                    "FETCH" => self.fetch(rest, true).await,
+                    "STORE" => self.store(rest, true).await,
+                    _ => Ok(Reply::Bad("unsupported UID command".into())),
+                }
+            }
+            _ => Ok(Reply::Bad("unknown command".into())),
+        }
+    }
+
+    /// List every message of the logged in address, oldest first.
+    async fn load(&self, read_only: bool) -> Result<Mailbox, Error> {
+        let client = self.client.as_ref().unwrap();
+        let mut emails = client.get_all_emails().await?;
+        emails.sort_by_key(|email| email.received_at);
+
+        let mut uids = self.server.uids.lock().unwrap();
+        let uids = uids.entry(client.email.to_string()).or_default();
+        let mut messages: Vec<_> = emails
+            .into_iter()
+            .map(|email| Message {
+                uid: uids.uid(&email.id),
+                email,
+                seen: false,
+                deleted: false,
+                raw: None,
+            })
+            .collect();
+        messages.sort_by_key(|message| message.uid);
+        Ok(Mailbox {
+            read_only,
+            messages,
+        })
+    }
+
+    /// Report messages that arrived or disappeared since the mailbox was selected.
+    async fn refresh(&mut self) -> Result<(), Error> {
+        let fresh = self.load(true).await?;
+        let mailbox = self.mailbox.as_mut().unwrap();
+        let mut untagged = String::new();
+        for seq in (1..=mailbox.messages.len()).rev() {
+            let uid = mailbox.messages[seq - 1].uid;
+            if !fresh.messages.iter().any(|m| m.uid == uid) {
+                mailbox.messages.remove(seq - 1);
+                untagged.push_str(&format!("* {seq} EXPUNGE\r\n"));
+            }
+        }
+        let last = mailbox.messages.last().map_or(0, |m| m.uid);
+        let count = mailbox.messages.len();
+        mailbox
+            .messages
+            .extend(fresh.messages.into_iter().filter(|m| m.uid > last));
+        if mailbox.messages.len() != count {
+            untagged.push_str(&format!("* {} EXISTS\r\n", mailbox.messages.len()));
+        }
+        self.write(&untagged).await
+    }
+
+    async fn expunge(&mut self, silent: bool) -> Result<Reply, Error> {
+        let client = self.client.clone().unwrap();
+        let mailbox = self.mailbox.as_mut().unwrap();
+        if mailbox.read_only {
+            return Ok(if silent {
+                Reply::Ok("CLOSE completed".into())
+            } else {
+                Reply::No("mailbox is read-only".into())
+            });
+        }
+        let mut untagged = String::new();
+        let mut failed = 0;
+        for seq in (1..=mailbox.messages.len()).rev() {
+            if !mailbox.messages[seq - 1].deleted {
+                continue;
+            }
+            match client
+                .delete_inbox(&mailbox.messages[seq - 1].email.id)
+                .await
+            {
+                Ok(()) => {
+                    mailbox.messages.remove(seq - 1);
+                    untagged.push_str(&format!("* {seq} EXPUNGE\r\n"));
+                }
+                Err(_) => failed += 1,
+            }
+        }
+        if !silent {
+            self.write(&untagged).await?;
+        }
+        if failed > 0 {
+            Ok(Reply::No(format!("{failed} messages could not be deleted")))
+        } else {
+            Ok(Reply::Ok("EXPUNGE completed".into()))
+        }
+    }
+
+    async fn search(&mut self, args: &str, uid: bool) -> Result<Reply, Error> {
+        let mailbox = self.mailbox.as_ref().unwrap();
+        let largest_uid = mailbox.messages.last().map_or(0, |m| m.uid);
+        let count = mailbox.messages.len() as u32;
+        let mut criteria = Tokens::new(args, vec![]);
+        let mut matches: Vec<bool> = vec![true; mailbox.messages.len()];
+        while let Some(key) = criteria.next() {
+            let key = key.to_ascii_uppercase();
+            let filter: Filter = match key.as_str() {
+                "ALL" => Box::new(|_, _| true),
+                "CHARSET" => {
+                    criteria.next();
+                    continue;
+                }
+                "SEEN" => Box::new(|_, m| m.seen),
+                "UNSEEN" => Box::new(|_, m| !m.seen),
+                "DELETED" => Box::new(|_, m| m.deleted),
+                "UNDELETED" => Box::new(|_, m| !m.deleted),
+                "UID" => match criteria.next().and_then(|s| Set::parse(&s, largest_uid)) {
+                    Some(set) => Box::new(move |_, m| set.contains(m.uid)),
+                    None => return Ok(Reply::Bad("invalid UID set".into())),
+                },
+                key => match Set::parse(key, count) {
+                    Some(set) => Box::new(move |seq, _| set.contains(seq)),
+                    None => return Ok(Reply::Bad(format!("unsupported search key {key}"))),
+                },
+            };
+            for (i, message) in mailbox.messages.iter().enumerate() {
+                matches[i] &= filter(i as u32 + 1, message);
+            }
+        }
+        let found: Vec<String> = mailbox
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| matches[*i])
+            .map(|(i, m)| if uid { m.uid } else { i as u32 + 1 }.to_string())
+            .collect();
+        let mut line = "* SEARCH".to_owned();
+        for n in found {
+            line.push(' ');
+            line.push_str(&n);
+        }
+        line.push_str("\r\n");
+        self.write(&line).await?;
+        Ok(Reply::Ok("SEARCH completed".into()))
+    }
+
+    async fn fetch(&mut self, args: &str, uid: bool) -> Result<Reply, Error> {
+        let Some((set, items)) = args.split_once(' ') else {
+            return Ok(Reply::Bad("expected a message set and items".into()));
+        };
+        let mut items = fetch_items(items);
+        if uid && !items.iter().any(|item| item == "UID") {
+            items.insert(0, "UID".into());
+        }
+        let selected = self.selected(set, uid);
+        let Some(selected) = selected else {
+            return Ok(Reply::Bad("invalid message set".into()));
+        };
+        let client = self.client.clone().unwrap();
+        for seq in selected {
+            let mut response = format!("* {} FETCH (", seq + 1);
+            for (n, item) in items.iter().enumerate() {
+                if n > 0 {
+                    response.push(' ');
+                }
+                let mailbox = self.mailbox.as_mut().unwrap();
+                let read_only = mailbox.read_only;
+                let message = &mut mailbox.messages[seq];
+                match fetch_item(&client, message, item, read_only).await? {
+                    Some(value) => response.push_str(&value),
+                    None => return Ok(Reply::Bad(format!("unsupported fetch item {item}"))),
+                }
+            }
+            response.push_str(")\r\n");
+            self.write(&response).await?;
+        }
+        Ok(Reply::Ok("FETCH completed".into()))
+    }
+
+    async fn store(&mut self, args: &str, uid: bool) -> Result<Reply, Error> {
+        let mut parts = args.splitn(3, ' ');
+        let (Some(set), Some(action), Some(flags)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Ok(Reply::Bad(
+                "expected a message set, an action and flags".into(),
+            ));
+        };
+        let Some(selected) = self.selected(set, uid) else {
+            return Ok(Reply::Bad("invalid message set".into()));
+        };
+        let action = action.to_ascii_uppercase();
+        let silent = action.ends_with(".SILENT");
+        let action = action.trim_end_matches(".SILENT");
+        if !["FLAGS", "+FLAGS", "-FLAGS"].contains(&action) {
+            return Ok(Reply::Bad("invalid STORE action".into()));
+        }
+        let flags = flags.trim_matches(['(', ')']).to_ascii_uppercase();
+        let flags: Vec<&str> = flags.split_whitespace().collect();
+        let mailbox = self.mailbox.as_mut().unwrap();
+        if mailbox.read_only {
+            return Ok(Reply::No("mailbox is read-only".into()));
+        }
+        let mut untagged = String::new();
+        for seq in selected {
+            let message = &mut mailbox.messages[seq];
+            for (flag, value) in [
+                ("\\SEEN", &mut message.seen),
+                ("\\DELETED", &mut message.deleted),
+            ] {
+                let listed = flags.contains(&flag);
+                match action {
+                    "FLAGS" => *value = listed,
+                    "+FLAGS" if listed => *value = true,
+                    "-FLAGS" if listed => *value = false,
+                    _ => {}
+                }
+            }
+            if !silent {
+                let uid = if uid {
+                    format!("UID {} ", message.uid)
+                } else {
+                    String::new()
+                };
+                untagged.push_str(&format!(
+                    "* {} FETCH ({uid}FLAGS {})\r\n",
+                    seq + 1,
+                    message.flags()
+                ));
+            }
+        }
+        self.write(&untagged).await?;
+        Ok(Reply::Ok("STORE completed".into()))
+    }
+
+    /// Indexes of the messages in a sequence or UID set.
+    fn selected(&self, set: &str, uid: bool) -> Option<Vec<usize>> {
+        let messages = &self.mailbox.as_ref().unwrap().messages;
+        let largest = if uid {
+            messages.last().map_or(0, |m| m.uid)
+        } else {
+            messages.len() as u32
+        };
+        let set = Set::parse(set, largest)?;
+        Some(
+            messages
+                .iter()
+                .enumerate()
+                .filter(|(i, m)| set.contains(if uid { m.uid } else { *i as u32 + 1 }))
+                .map(|(i, _)| i)
+                .collect(),
+        )
+    }
+}
+
+/// A SEARCH criterion, called with the sequence number and the message.
+type Filter = Box<dyn Fn(u32, &Message) -> bool>;
+
+enum Reply {
+    Ok(String),
+    No(String),
+    Bad(String),
+    Logout,
+}
+
+/// Render a single FETCH data item, `None` when the item isn't supported.
+async fn fetch_item(
+    client: &Client,
+    message: &mut Message,
+    item: &str,
+    read_only: bool,
+) -> Result<Option<String>, Error> {
+    let upper = item.to_ascii_uppercase();
+    let value = match upper.as_str() {
+        "UID" => format!("UID {}", message.uid),
+        "FLAGS" => format!("FLAGS {}", message.flags()),
+        "INTERNALDATE" => format!(
+            "INTERNALDATE \"{}\"",
+            internal_date(message.email.received_at)
+        ),
+        "RFC822.SIZE" => format!("RFC822.SIZE {}", raw(client, message).await?.len()),
+        "ENVELOPE" => format!("ENVELOPE {}", envelope(&message.email)),
+        "RFC822" | "RFC822.HEADER" | "RFC822.TEXT" => {
+            let section = match upper.as_str() {
+                "RFC822" => "",
+                "RFC822.HEADER" => "HEADER",
+                _ => "TEXT",
+            };
+            if upper != "RFC822.HEADER" && !read_only {
+                message.seen = true;
+            }
+            let content = section_content(raw(client, message).await?, section);
+            format!("{upper} {}", literal(&content))
+        }
+        _ if upper.starts_with("BODY[") || upper.starts_with("BODY.PEEK[") => {
+            let peek = upper.starts_with("BODY.PEEK[");
+            let open = item.find('[').unwrap();
+            let Some(close) = item.rfind(']') else {
+                return Ok(None);
+            };
+            let section = &item[open + 1..close];
+            let partial = &item[close + 1..];
+            if !peek && !read_only {
+                message.seen = true;
+            }
+            let content = section_content(raw(client, message).await?, section);
+            let (content, origin) = match parse_partial(partial) {
+                Some((start, length)) => {
+                    let start = start.min(content.len());
+                    (
+                        partial_content(&content, start, length),
+                        format!("<{start}>"),
+                    )
+                }
+                None if partial.is_empty() => (content, String::new()),
+                None => return Ok(None),
+            };
+            format!("BODY[{section}]{origin} {}", literal(&content))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+async fn raw<'a>(client: &Client, message: &'a mut Message) -> Result<&'a str, Error> {
+    if message.raw.is_none() {
+        message.raw = Some(client.render_message(&message.email).await?);
+    }
+    Ok(message.raw.as_deref().unwrap())
+}
+
+/// Extract a body section: the whole message, `HEADER`, `TEXT` or `HEADER.FIELDS[.NOT] (...)`.
+fn section_content(raw: &str, section: &str) -> String {
+    let split = raw.find("\r\n\r\n").map_or(raw.len(), |i| i + 4);
+    let (header, text) = raw.split_at(split);
+    let upper = section.to_ascii_uppercase();
+    if upper.is_empty() {
+        raw.to_owned()
+    } else if upper == "HEADER" {
+        header.to_owned()
+    } else if upper == "TEXT" {
+        text.to_owned()
+    } else if let Some(fields) = upper.strip_prefix("HEADER.FIELDS") {
+        let (not, fields) = match fields.strip_prefix(".NOT") {
+            Some(fields) => (true, fields),
+            None => (false, fields),
+        };
+        let fields: Vec<&str> = fields
+            .trim()
+            .trim_matches(['(', ')'])
+            .split_whitespace()
+            .collect();
+        let mut content: String = header
+            .split_inclusive("\r\n")
+            .filter(|line| {
+                let name = line
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_uppercase();
+                !line.trim().is_empty() && fields.contains(&name.as_str()) != not
+            })
+            .collect();
+        content.push_str("\r\n");
+        content
+    } else {
+        String::new()
+    }
+}
+
+/// `<start.length>` suffix of a `BODY[]` item.
+fn parse_partial(partial: &str) -> Option<(usize, usize)> {
+    let (start, length) = partial
+        .strip_prefix('<')?
+        .strip_suffix('>')?
+        .split_once('.')?;
+    Some((start.parse().ok()?, length.parse().ok()?))
+}
+
+/// Split a FETCH item list, expanding the `ALL`, `FAST` and `FULL` macros.
+fn fetch_items(items: &str) -> Vec<String> {
+    let items = items.trim();
+    let items = match items.strip_prefix('(') {
+        Some(inner) => inner.strip_suffix(')').unwrap_or(inner),
+        None => items,
+    };
+    let mut split = vec![];
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in items.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            ' ' if depth == 0 => {
+                split.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    split.push(current);
+    split
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| match item.to_ascii_uppercase().as_str() {
+            "ALL" | "FULL" => vec!["FLAGS", "INTERNALDATE", "RFC822.SIZE", "ENVELOPE"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            "FAST" => vec!["FLAGS", "INTERNALDATE", "RFC822.SIZE"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            _ => vec![item],
+        })
+        .collect()
+}
+
+fn envelope(email: &Email) -> String {
+    let date = email
+        .received_at
+        .format(&time::format_description::well_known::Rfc2822)
+        .unwrap_or_default();
+    let from = address(&email.from_address);
+    let to = address(&email.to_address);
+    format!(
+        "({} {} {from} {from} {from} {to} NIL NIL NIL {})",
+        quote(&date),
+        quote(&email.subject),
+        quote(&format!("<{}@barid.site>", email.id))
+    )
+}
+
+fn address(address: &EmailAddress) -> String {
+    let name = address.display_name().map_or("NIL".to_owned(), quote);
+    format!(
+        "(({name} NIL {} {}))",
+        quote(address.local_part()),
+        quote(address.domain())
+    )
+}
+
+/// `dd-Mon-yyyy hh:mm:ss +0000`
+fn internal_date(date: OffsetDateTime) -> String {
+    let date = date.to_offset(time::UtcOffset::UTC);
+    let month = date.month().to_string();
+    format!(
+        "{:02}-{}-{} {:02}:{:02}:{:02} +0000",
+        date.day(),
+        &month[..3],
+        date.year(),
+        date.hour(),
+        date.minute(),
+        date.second()
+    )
+}
+
+/// A quoted string, or a literal when the value can't be quoted.
+fn quote(value: &str) -> String {
+    if value.contains(['\r', '\n']) || !value.is_ascii() {
+        literal(value)
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// `length` bytes from `start`. Offsets are chosen by the client and may split a character,
+/// which is then replaced, the rendered messages are ASCII so this doesn't happen in practice.
+fn partial_content(content: &str, start: usize, length: usize) -> String {
+    let bytes = content.as_bytes();
+    let start = start.min(bytes.len());
+    let end = start.saturating_add(length).min(bytes.len());
+    String::from_utf8_lossy(&bytes[start..end]).into_owned()
+}
+
+fn literal(value: &str) -> String {
+    format!("{{{}}}\r\n{value}", value.len())
+}
+
+fn is_inbox(name: Option<String>) -> bool {
+    name.is_some_and(|name| name.eq_ignore_ascii_case("INBOX"))
+}
+
+/// `{n}` or `{n+}` at the end of a line.
+fn literal_size(line: &str) -> Option<(usize, bool)> {
+    let inner = line.strip_suffix('}')?;
+    let open = inner.rfind('{')?;
+    let size = &inner[open + 1..];
+    match size.strip_suffix('+') {
+        Some(size) => Some((size.parse().ok()?, false)),
+        None => Some((size.parse().ok()?, true)),
+    }
+}
+
+/// Splits command arguments into atoms, quoted strings and literals.
+struct Tokens<'a> {
+    rest: &'a str,
+    literals: std::vec::IntoIter<String>,
+}
+impl<'a> Tokens<'a> {
+    fn new(args: &'a str, literals: Vec<String>) -> Self {
+        Self {
+            rest: args,
+            literals: literals.into_iter(),
+        }
+    }
+}
+impl Iterator for Tokens<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.rest = self.rest.trim_start();
+        if self.rest.is_empty() {
+            return None;
+        }
+        if let Some(rest) = self.rest.strip_prefix("{}") {
+            self.rest = rest;
+            return self.literals.next();
+        }
+        if let Some(quoted) = self.rest.strip_prefix('"') {
+            let mut token = String::new();
+            let mut chars = quoted.char_indices();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => token.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        self.rest = &quoted[i + 1..];
+                        return Some(token);
+                    }
+                    c => token.push(c),
+                }
+            }
+            self.rest = "";
+            return Some(token);
+        }
+        let end = self.rest.find(' ').unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(token.to_owned())
+    }
+}
+
+async fn serve(server: Arc<Server>, stream: TcpStream) -> Result<(), Error> {
+    let (reader, writer) = stream.into_split();
+    let mut session = Session {
+        server,
+        client: None,
+        mailbox: None,
+        reader: BufReader::new(reader),
+        writer,
+    };
+    session.run().await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let mut listen = "127.0.0.1:1143".to_owned();
+    let mut password = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().ok_or("--listen needs an address")?,
+            "--password" => password = Some(args.next().ok_or("--password needs a value")?),
+            _ => {
+                eprintln!("usage: tmapi-imap [--listen <address>] [--password <password>]");
+                std::process::exit(2);
+            }
+        }
+    }
+    let uid_validity = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+    let server = Arc::new(Server {
+        password,
+        uid_validity,
+        uids: Mutex::default(),
+    });
+    let listener = TcpListener::bind(&listen).await?;
+    eprintln!("listening on {listen}");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(error) = serve(server, stream).await {
+                eprintln!("{peer}: {error}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_sets() {
+        let set = Set::parse("1:3,7,9:*", 12).unwrap();
+        assert!(set.contains(2));
+        assert!(set.contains(7));
+        assert!(set.contains(12));
+        assert!(!set.contains(8));
+        assert_eq!(Set::parse("*:4", 2), Some(Set(vec![(2, 4)])));
+        assert_eq!(Set::parse("a", 2), None);
+    }
+    #[test]
+    fn tokens() {
+        let tokens: Vec<_> =
+            Tokens::new(r#"y@iusearch.lol "pass \"word\"" {}"#, vec!["lit".into()]).collect();
+        assert_eq!(tokens, ["y@iusearch.lol", "pass \"word\"", "lit"]);
+    }
+    #[test]
+    fn fetch_item_list() {
+        assert_eq!(
+            fetch_items("(UID BODY.PEEK[HEADER.FIELDS (FROM TO)] FAST)"),
+            [
+                "UID",
+                "BODY.PEEK[HEADER.FIELDS (FROM TO)]",
+                "FLAGS",
+                "INTERNALDATE",
+                "RFC822.SIZE"
+            ]
+        );
+        assert_eq!(fetch_items("BODY[]<0.10>"), ["BODY[]<0.10>"]);
+    }
+    #[test]
+    fn header_fields() {
+        let raw = "From: a@b.c\r\nTo: d@e.f\r\nSubject: Hi\r\n\r\nbody";
+        assert_eq!(
+            section_content(raw, "HEADER.FIELDS (FROM SUBJECT)"),
+            "From: a@b.c\r\nSubject: Hi\r\n\r\n"
+        );
+        assert_eq!(
+            section_content(raw, "HEADER.FIELDS.NOT (FROM SUBJECT)"),
+            "To: d@e.f\r\n\r\n"
+        );
+        assert_eq!(section_content(raw, "TEXT"), "body");
+    }
+    #[test]
+    fn partial_fetch_splits_bytes() {
+        assert_eq!(partial_content("héllo", 0, 2), "h\u{FFFD}");
+        assert_eq!(partial_content("héllo", 3, 100), "llo");
+        assert_eq!(partial_content("héllo", 100, 1), "");
+    }
+    #[test]
+    fn literals() {
+        assert_eq!(literal_size("a LOGIN {5}"), Some((5, true)));
+        assert_eq!(literal_size("a LOGIN {5+}"), Some((5, false)));
+        assert_eq!(literal_size("a NOOP"), None);
+    }
+    #[test]
+    fn internal_dates() {
+        assert_eq!(
+            internal_date(OffsetDateTime::UNIX_EPOCH),
+            "01-Jan-1970 00:00:00 +0000"
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! `tmapi-pop3`: a POP3 server in front of barid inboxes.
+//!
+//! ```text
+//! tmapi-pop3 [--listen 127.0.0.1:1110] [--password <password>]
+//! ```
+//!
+//! Log in with a barid address as the user name, any password is accepted unless
+//! `--password` is given. Messages marked with `DELE` are removed with
+//! [`Client::delete_inbox`] when the session ends with `QUIT`.
+//!
+//! Supported commands: `CAPA`, `USER`, `PASS`, `STAT`, `LIST`, `UIDL`, `RETR`, `TOP`, `DELE`,
+//! `NOOP`, `RSET` and `QUIT`.
+use std::sync::Arc;
+
+use tmapi::{Client, Email};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug)]
+struct Config {
+    password: Option<String>,
+}
+
+#[derive(Debug)]
+struct Message {
+    email: Email,
+    deleted: bool,
+    raw: Option<String>,
+    size: Option<usize>,
+}
+
+enum State {
+    Authorization {
+        user: Option<String>,
+    },
+    Transaction {
+        client: Client,
+        messages: Vec<Message>,
+    },
+}
+
+struct Session {
+    config: Arc<Config>,
+    state: State,
+    reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+    writer: tokio::net::tcp::OwnedWriteHalf,
+}
+
+impl Session {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.write("+OK tmapi POP3 bridge ready\r\n").await?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let mut args = line.split_whitespace();
+            let command = args.next().unwrap_or_default().to_ascii_uppercase();
+            let args: Vec<String> = args.map(String::from).collect();
+            if command == "QUIT" {
+                let reply = self.quit().await;
+                self.write(&reply).await?;
+                return Ok(());
+            }
+            let reply = match self.handle(&command, &args).await {
+                Ok(reply) => reply,
+                Err(error) => format!("-ERR {error}\r\n"),
+            };
+            self.write(&reply).await?;
+        }
+    }
+
+    async fn write(&mut self, data: &str) -> Result<(), Error> {
+        self.writer.write_all(data.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn handle(&mut self, command: &str, args: &[String]) -> Result<String, Error> {
+        if command == "CAPA" {
+            return Ok("+OK capabilities follow\r\nUSER\r\nUIDL\r\nTOP\r\n.\r\n".into());
+        }
+        if command == "NOOP" {
+            return Ok("+OK\r\n".into());
+        }
+        match &mut self.state {
+            State::Authorization { user } => match command {
+                "USER" => match args.first() {
+                    Some(name) => {
+                        *user = Some(name.clone());
+                        Ok("+OK send PASS\r\n".into())
+                    }
+                    None => Ok("-ERR expected a user name\r\n".into()),
+                },
+                "PASS" => {
+                    let Some(name) = user.take() else {
+                        return Ok("-ERR send USER first\r\n".into());
+                    };
+                    let password = args.join(" ");
+                    if self
+                        .config
+                        .password
+                        .as_ref()
+                        .is_some_and(|p| *p != password)
+                    {
+                        return Ok("-ERR invalid credentials\r\n".into());
+                    }
+                    let client = match Client::new(&name) {
+                        Ok(client) => client,
+                        Err(error) => return Ok(format!("-ERR {error}\r\n")),
+                    };
+                    let messages = maildrop(&client).await?;
+                    let reply = format!("+OK {} messages\r\n", messages.len());
+                    self.state = State::Transaction { client, messages };
+                    Ok(reply)
+                }
+                _ => Ok("-ERR log in first\r\n".into()),
+            },
+            State::Transaction { client, messages } => match command {
+                "STAT" => {
+                    let (mut count, mut size) = (0, 0);
+                    for message in messages.iter_mut().filter(|m| !m.deleted) {
+                        count += 1;
+                        size += size_of(client, message).await?;
+                    }
+                    Ok(format!("+OK {count} {size}\r\n"))
+                }
+                "LIST" | "UIDL" => match args.first() {
+                    Some(n) => match message(messages, n) {
+                        Some((n, message)) => Ok(format!(
+                            "+OK {}\r\n",
+                            listing(command, client, n, message).await?
+                        )),
+                        None => Ok("-ERR no such message\r\n".into()),
+                    },
+                    None => {
+                        let mut reply = "+OK\r\n".to_owned();
+                        for (i, message) in messages.iter_mut().enumerate() {
+                            if !message.deleted {
+                                reply.push_str(&listing(command, client, i + 1, message).await?);
+                                reply.push_str("\r\n");
+                            }
+                        }
+                        reply.push_str(".\r\n");
+                        Ok(reply)
+                    }
+                },
+                "RETR" | "TOP" => {
+                    let Some((_, message)) = args.first().and_then(|n| message(messages, n)) else {
+                        return Ok("-ERR no such message\r\n".into());
+                    };
+                    let raw = raw(client, message).await?;
+                    let content = match (command, args.get(1).map(|n| n.parse::<usize>())) {
+                        ("RETR", _) => raw.to_owned(),
+                        (_, Some(Ok(lines))) => top(raw, lines),
+                        _ => return Ok("-ERR expected a message and a line count\r\n".into()),
+                    };
+                    Ok(format!("+OK\r\n{}.\r\n", dot_stuff(&content)))
+                }
+                "DELE" => match args.first().and_then(|n| message(messages, n)) {
+                    Some((_, message)) => {
+                        message.deleted = true;
+                        Ok("+OK marked for deletion\r\n".into())
+                    }
+                    None => Ok("-ERR no such message\r\n".into()),
+                },
+                "RSET" => {
+                    messages.iter_mut().for_each(|m| m.deleted = false);
+                    Ok("+OK\r\n".into())
+                }
+                _ => Ok("-ERR unknown command\r\n".into()),
+            },
+        }
+    }
+
+    /// Enter the UPDATE state: delete the marked messages.
+    async fn quit(&mut self) -> String {
+        let State::Transaction { client, messages } = &self.state else {
+            return "+OK bye\r\n".into();
+        };
+        let mut failed = 0;
+        for message in messages.iter().filter(|m| m.deleted) {
+            if client.delete_inbox(&message.email.id).await.is_err() {
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            format!("-ERR {failed} messages could not be deleted\r\n")
+        } else {
+            "+OK bye\r\n".into()
+        }
+    }
+}
+
+/// Every message of the address, oldest first.
+async fn maildrop(client: &Client) -> Result<Vec<Message>, Error> {
+    let mut emails = client.get_all_emails().await?;
+    emails.sort_by_key(|email| email.received_at);
+    Ok(emails
+        .into_iter()
+        .map(|email| Message {
+            email,
+            deleted: false,
+            raw: None,
+            size: None,
+        })
+        .collect())
+}
+
+/// A `LIST` (number and size) or `UIDL` (number and message ID) line.
+async fn listing(
+    command: &str,
+    client: &Client,
+    n: usize,
+    message: &mut Message,
+) -> Result<String, Error> {
+    if command == "LIST" {
+        Ok(format!("{n} {}", size_of(client, message).await?))
+    } else {
+        Ok(format!("{n} {}", message.email.id))
+    }
+}
+
+/// Look up a message by its 1-based number, skipping deleted ones.
+fn message<'a>(messages: &'a mut [Message], n: &str) -> Option<(usize, &'a mut Message)> {
+    let n: usize = n.parse().ok()?;
+    let message = messages.get_mut(n.checked_sub(1)?)?;
+    (!message.deleted).then_some((n, message))
+}
+
+async fn raw<'a>(client: &Client, message: &'a mut Message) -> Result<&'a str, Error> {
+    if message.raw.is_none() {
+        message.raw = Some(client.render_message(&message.email).await?);
+    }
+    Ok(message.raw.as_deref().unwrap())
+}
+
+/// The size of the rendered message, computed from the attachment metadata so that `LIST` and
+/// `STAT` don't download every attachment.
+async fn size_of(client: &Client, message: &mut Message) -> Result<usize, Error> {
+    if let Some(raw) = &message.raw {
+        return Ok(raw.len());
+    }
+    if let Some(size) = message.size {
+        return Ok(size);
+    }
+    let mut attachments = vec![];
+    if message.email.has_attachments {
+        attachments = client.get_attachments(&message.email.id).await?;
+    }
+    let encoded: usize = attachments
+        .iter()
+        .map(|attachment| base64_len(attachment.size as usize))
+        .sum();
+    let empty: Vec<_> = attachments.into_iter().map(|a| (a, vec![])).collect();
+    let size = message.email.to_rfc5322(&empty).len() + encoded;
+    message.size = Some(size);
+    Ok(size)
+}
+
+/// The length of `size` bytes encoded as MIME base64, with a CRLF after every 76 characters.
+fn base64_len(size: usize) -> usize {
+    let encoded = size.div_ceil(3) * 4;
+    encoded + encoded.div_ceil(76) * 2
+}
+
+/// The headers, the blank line and the first `lines` lines of the body.
+fn top(raw: &str, lines: usize) -> String {
+    let split = raw.find("\r\n\r\n").map_or(raw.len(), |i| i + 4);
+    let (header, body) = raw.split_at(split);
+    let body: String = body.split_inclusive("\r\n").take(lines).collect();
+    format!("{header}{body}")
+}
+
+/// Escape lines starting with `.` and make sure the content ends with CRLF.
+fn dot_stuff(content: &str) -> String {
+    let mut stuffed = String::with_capacity(content.len() + 2);
+    for line in content.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            stuffed.push('.');
+        }
+        stuffed.push_str(line);
+    }
+    if !stuffed.ends_with("\r\n") {
+        stuffed.push_str("\r\n");
+    }
+    stuffed
+}
+
+async fn serve(config: Arc<Config>, stream: TcpStream) -> Result<(), Error> {
+    let (reader, writer) = stream.into_split();
+    let mut session = Session {
+        config,
+        state: State::Authorization { user: None },
+        reader: BufReader::new(reader),
+        writer,
+    };
+    session.run().await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let mut listen = "127.0.0.1:1110".to_owned();
+    let mut password = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().ok_or("--listen needs an address")?,
+            "--password" => password = Some(args.next().ok_or("--password needs a value")?),
+            _ => {
+                eprintln!("usage: tmapi-pop3 [--listen <address>] [--password <password>]");
+                std::process::exit(2);
+            }
+        }
+    }
+    let config = Arc::new(Config { password });
+    let listener = TcpListener::bind(&listen).await?;
+    eprintln!("listening on {listen}");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(error) = serve(config, stream).await {
+                eprintln!("{peer}: {error}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_stuffing() {
+        assert_eq!(dot_stuff("a\r\n.b\r\n..c"), "a\r\n..b\r\n...c\r\n");
+    }
+    #[test]
+    fn sizes_match_rendering() {
+        let email = Email {
+            id: "abc".into(),
+            from_address: "sender@example.com".parse().unwrap(),
+            to_address: "y@iusearch.lol".parse().unwrap(),
+            subject: "Hi".into(),
+            received_at: time::OffsetDateTime::UNIX_EPOCH,
+            html_content: None,
+            text_content: Some("Hi".into()),
+            has_attachments: true,
+            attachment_count: 1,
+        };
+        for size in [0, 1, 2, 3, 56, 57, 58, 1000] {
+            let attachment = tmapi::Attachment {
+                content_type: "application/octet-stream".into(),
+                created_at: time::OffsetDateTime::UNIX_EPOCH,
+                filename: "a.bin".into(),
+                id: "a".into(),
+                size: size as u64,
+            };
+            let empty = email.to_rfc5322(&[(attachment.clone(), vec![])]);
+            let full = email.to_rfc5322(&[(attachment, vec![7; size])]);
+            assert_eq!(empty.len() + base64_len(size), full.len());
+        }
+    }
+    #[test]
+    fn top_lines() {
+        let raw = "Subject: Hi\r\n\r\none\r\ntwo\r\nthree\r\n";
+        assert_eq!(top(raw, 0), "Subject: Hi\r\n\r\n");
+        assert_eq!(top(raw, 2), "Subject: Hi\r\n\r\none\r\ntwo\r\n");
+    }
+}
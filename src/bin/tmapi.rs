@@ -0,0 +1,46 @@
+//! `tmapi`: command line helpers for barid inboxes.
+//!
+//! ```text
+//! tmapi doctor <address>   check that barid is up and the address is usable
+//! tmapi stats <address>    summarize the inbox: senders, attachments and arrivals
+//! ```
+use std::process::ExitCode;
+
+use tmapi::Client;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const USAGE: &str = "usage: tmapi <doctor|stats> <address>";
+
+async fn doctor(client: &Client) -> ExitCode {
+    let report = client.ensure_ready().await;
+    println!("{report}");
+    if report.is_ready() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn stats(client: &Client) -> Result<ExitCode, Error> {
+    print!("{}", client.stats().await?);
+    Ok(ExitCode::SUCCESS)
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode, Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(command), Some(address)) = (args.first(), args.get(1)) else {
+        eprintln!("{USAGE}");
+        return Ok(ExitCode::from(2));
+    };
+    let client = Client::new(address)?;
+    match command.as_str() {
+        "doctor" => Ok(doctor(&client).await),
+        "stats" => stats(&client).await,
+        _ => {
+            eprintln!("{USAGE}");
+            Ok(ExitCode::from(2))
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! `tmapi-tui`: browse barid inboxes from the terminal.
+//!
+//! ```text
+//! tmapi-tui [--interval <seconds>] [--save-dir <dir>] <address>...
+//! ```
+//!
+//! Keys:
+//! - `↑`/`↓` select a message, `PgUp`/`PgDn` scroll the reader pane
+//! - `Tab` switch to the next address, `o` open another address
+//! - `d` delete the message, `D` purge the inbox
+//! - `a` list the message's attachments, `s` save them to the save directory
+//!   (`tmapi-attachments/` by default), never overwriting files
+//! - `y` copy a link or one-time code from the message (through OSC 52)
+//! - `r` refresh now, `q` quit
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+use tmapi::{Attachment, Client, Email};
+use tokio::runtime::Runtime;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// What the keyboard currently drives.
+enum Mode {
+    Normal,
+    Confirm(Action),
+    Picker(Vec<String>, ListState),
+    Input(String),
+}
+
+#[derive(Clone, Copy)]
+enum Action {
+    Delete,
+    Purge,
+}
+
+/// Results of background requests, see [`App::spawn`].
+enum Update {
+    Emails(Result<Vec<Email>, String>),
+    /// The attachments of the message with this ID
+    Attachments(String, Result<Vec<Attachment>, String>),
+    /// A message for the status line, and whether the message list changed
+    Done(String, bool),
+}
+
+struct App {
+    runtime: Runtime,
+    clients: Vec<Client>,
+    current: usize,
+    emails: Vec<Email>,
+    list: ListState,
+    scroll: u16,
+    attachments: Option<(String, Vec<Attachment>)>,
+    mode: Mode,
+    status: String,
+    interval: Duration,
+    save_dir: PathBuf,
+    last_refresh: Option<Instant>,
+    /// Updates tagged with the address they belong to
+    updates: mpsc::Sender<(String, Update)>,
+    updated: mpsc::Receiver<(String, Update)>,
+}
+
+impl App {
+    fn client(&self) -> &Client {
+        &self.clients[self.current]
+    }
+
+    fn selected(&self) -> Option<&Email> {
+        self.list.selected().and_then(|i| self.emails.get(i))
+    }
+
+    /// Run `task` on the runtime without blocking the UI, its update is applied by
+    /// [`App::apply_updates`] unless the address changed in the meantime.
+    fn spawn<F>(&self, task: impl FnOnce(Client) -> F)
+    where
+        F: Future<Output = Update> + Send + 'static,
+    {
+        let client = self.client().clone();
+        let address = client.email.to_string();
+        let updates = self.updates.clone();
+        let task = task(client);
+        self.runtime.spawn(async move {
+            let _ = updates.send((address, task.await));
+        });
+    }
+
+    /// Fetch the message list in the background.
+    fn refresh(&mut self) {
+        self.last_refresh = Some(Instant::now());
+        self.spawn(|client| async move {
+            Update::Emails(client.get_all_emails().await.map_err(|e| e.to_string()))
+        });
+    }
+
+    fn apply_updates(&mut self) {
+        while let Ok((address, update)) = self.updated.try_recv() {
+            if address != self.client().email.to_string() {
+                continue;
+            }
+            match update {
+                Update::Emails(Ok(emails)) => {
+                    let selected_id = self.selected().map(|email| email.id.clone());
+                    self.emails = emails;
+                    let index = selected_id
+                        .and_then(|id| self.emails.iter().position(|email| email.id == id))
+                        .or((!self.emails.is_empty()).then_some(0));
+                    self.list.select(index);
+                }
+                Update::Emails(Err(error)) => self.status = format!("refresh failed: {error}"),
+                Update::Attachments(id, Ok(attachments)) => {
+                    if self.selected().is_some_and(|email| email.id == id) {
+                        self.status.clear();
+                        self.attachments = Some((id, attachments));
+                    }
+                }
+                Update::Attachments(_, Err(error)) => {
+                    self.status = format!("listing attachments failed: {error}")
+                }
+                Update::Done(status, changed) => {
+                    self.status = status;
+                    if changed {
+                        self.refresh();
+                    }
+                }
+            }
+        }
+    }
+
+    fn switch(&mut self, index: usize) {
+        self.current = index;
+        self.emails.clear();
+        self.list.select(None);
+        self.attachments = None;
+        self.scroll = 0;
+        self.status = format!("switched to {}", self.client().email);
+        self.refresh();
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        self.refresh();
+        loop {
+            self.apply_updates();
+            if self
+                .last_refresh
+                .is_none_or(|last| last.elapsed() >= self.interval)
+            {
+                self.refresh();
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if !self.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns `false` when the app should quit.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Confirm(action) => {
+                if code == KeyCode::Char('y') {
+                    self.perform(action);
+                } else {
+                    self.status = "cancelled".into();
+                }
+            }
+            Mode::Picker(items, mut state) => match code {
+                KeyCode::Up => {
+                    state.select_previous();
+                    self.mode = Mode::Picker(items, state);
+                }
+                KeyCode::Down => {
+                    state.select_next();
+                    self.mode = Mode::Picker(items, state);
+                }
+                KeyCode::Enter => {
+                    if let Some(item) = state.selected().and_then(|i| items.get(i)) {
+                        copy_to_clipboard(item);
+                        self.status = format!("copied {item}");
+                    }
+                }
+                _ => {}
+            },
+            Mode::Input(mut input) => match code {
+                KeyCode::Enter => match Client::new(input.trim()) {
+                    Ok(client) => {
+                        let existing = self.clients.iter().position(|c| c.email == client.email);
+                        let index = existing.unwrap_or_else(|| {
+                            self.clients.push(client);
+                            self.clients.len() - 1
+                        });
+                        self.switch(index);
+                    }
+                    Err(error) => self.status = format!("invalid address: {error}"),
+                },
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.mode = Mode::Input(input);
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.mode = Mode::Input(input);
+                }
+                _ => self.mode = Mode::Input(input),
+            },
+            Mode::Normal => match code {
+                KeyCode::Char('q') | KeyCode::Esc => return false,
+                KeyCode::Up => self.select(self.list.selected().map(|i| i.saturating_sub(1))),
+                KeyCode::Down => self.select(
+                    self.list
+                        .selected()
+                        .map(|i| (i + 1).min(self.emails.len().saturating_sub(1))),
+                ),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::Tab => self.switch((self.current + 1) % self.clients.len()),
+                KeyCode::Char('o') => self.mode = Mode::Input(String::new()),
+                KeyCode::Char('r') => self.refresh(),
+                KeyCode::Char('d') if self.selected().is_some() => {
+                    self.mode = Mode::Confirm(Action::Delete)
+                }
+                KeyCode::Char('D') => self.mode = Mode::Confirm(Action::Purge),
+                KeyCode::Char('a') => self.load_attachments(),
+                KeyCode::Char('s') => self.save_attachments(),
+                KeyCode::Char('y') => {
+                    let items = self.selected().map(copyable).unwrap_or_default();
+                    if items.is_empty() {
+                        self.status = "no links or codes in this message".into();
+                    } else {
+                        self.mode =
+                            Mode::Picker(items, ListState::default().with_selected(Some(0)));
+                    }
+                }
+                _ => {}
+            },
+        }
+        true
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        if index != self.list.selected() {
+            self.scroll = 0;
+            self.attachments = None;
+        }
+        self.list.select(index);
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Delete => {
+                let Some(id) = self.selected().map(|email| email.id.clone()) else {
+                    return;
+                };
+                self.status = "deleting…".into();
+                self.spawn(|client| async move {
+                    let status = match client.delete_inbox(&id).await {
+                        Ok(()) => "message deleted".into(),
+                        Err(error) => format!("delete failed: {error}"),
+                    };
+                    Update::Done(status, true)
+                });
+            }
+            Action::Purge => {
+                self.status = "purging…".into();
+                self.spawn(|client| async move {
+                    let status = match client.delete_all_emails().await {
+                        Ok(count) => format!("{count} messages deleted"),
+                        Err(error) => format!("purge failed: {error}"),
+                    };
+                    Update::Done(status, true)
+                });
+            }
+        }
+    }
+
+    /// List the selected message's attachments in the background, unless they're loaded.
+    fn load_attachments(&mut self) {
+        let Some(id) = self.selected().map(|email| email.id.clone()) else {
+            return;
+        };
+        if self
+            .attachments
+            .as_ref()
+            .is_some_and(|(loaded, _)| *loaded == id)
+        {
+            return;
+        }
+        self.status = "listing attachments…".into();
+        self.spawn(|client| async move {
+            let attachments = client.get_attachments(&id).await;
+            Update::Attachments(id, attachments.map_err(|e| e.to_string()))
+        });
+    }
+
+    /// Download the selected message's attachments into the save directory in the background.
+    fn save_attachments(&mut self) {
+        let Some(id) = self.selected().map(|email| email.id.clone()) else {
+            return;
+        };
+        let loaded = self
+            .attachments
+            .as_ref()
+            .filter(|(loaded, _)| *loaded == id)
+            .map(|(_, attachments)| attachments.clone());
+        let save_dir = self.save_dir.clone();
+        self.status = "saving attachments…".into();
+        self.spawn(|client| async move {
+            let attachments = match loaded {
+                Some(attachments) => attachments,
+                None => match client.get_attachments(&id).await {
+                    Ok(attachments) => attachments,
+                    Err(error) => {
+                        return Update::Done(format!("listing attachments failed: {error}"), false);
+                    }
+                },
+            };
+            let mut status = format!(
+                "saved {} attachments to {}",
+                attachments.len(),
+                save_dir.display()
+            );
+            for attachment in &attachments {
+                let result = client
+                    .download_attachment(&attachment.id)
+                    .await
+                    .and_then(|content| Ok(save(&save_dir, &file_name(attachment), &content)?));
+                if let Err(error) = result {
+                    status = format!("saving {} failed: {error}", attachment.filename);
+                }
+            }
+            Update::Done(status, false)
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [list, reader] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(main);
+
+        let items: Vec<ListItem> = self
+            .emails
+            .iter()
+            .map(|email| {
+                ListItem::new(format!("{}  {}", email.from_address.email(), email.subject))
+            })
+            .collect();
+        let title = format!(" {} ({}) ", self.client().email, self.emails.len());
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            list,
+            &mut self.list,
+        );
+
+        let width = reader.width.saturating_sub(2) as usize;
+        let body = match self.selected() {
+            Some(email) => {
+                let mut lines = vec![
+                    Line::from(format!("From: {}", email.from_address)).bold(),
+                    Line::from(format!("To: {}", email.to_address)),
+                    Line::from(format!("Subject: {}", email.subject)).bold(),
+                ];
+                if let Some((_, attachments)) = &self.attachments {
+                    for attachment in attachments {
+                        lines.push(Line::from(format!(
+                            "📎 {} ({}, {} bytes)",
+                            attachment.filename, attachment.content_type, attachment.size
+                        )));
+                    }
+                } else if email.has_attachments {
+                    lines.push(Line::from(format!(
+                        "{} attachments, press `a` to list them",
+                        email.attachment_count
+                    )));
+                }
+                lines.push(Line::default());
+                lines.extend(
+                    email
+                        .plain_text(width)
+                        .lines()
+                        .map(|l| Line::from(l.to_owned())),
+                );
+                lines
+            }
+            None => vec![Line::from("No message selected")],
+        };
+        frame.render_widget(
+            Paragraph::new(body)
+                .block(Block::bordered())
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0)),
+            reader,
+        );
+
+        let help =
+            "q quit  tab/o address  d delete  D purge  a attachments  s save  y copy  r refresh";
+        let status_line = if self.status.is_empty() {
+            help.to_owned()
+        } else {
+            format!("{}  |  {help}", self.status)
+        };
+        frame.render_widget(Paragraph::new(status_line).reversed(), status);
+
+        match &mut self.mode {
+            Mode::Normal => {}
+            Mode::Confirm(action) => {
+                let question = match action {
+                    Action::Delete => "Delete this message? (y/n)",
+                    Action::Purge => "Delete every message of this address? (y/n)",
+                };
+                popup(frame, Paragraph::new(question), 50, 3);
+            }
+            Mode::Input(input) => {
+                let block = Block::bordered().title(" Address ");
+                let area = centered(frame.area(), 50, 3);
+                frame.render_widget(Clear, area);
+                frame.render_widget(Paragraph::new(input.as_str()).block(block), area);
+            }
+            Mode::Picker(items, state) => {
+                let height = items.len() as u16 + 2;
+                let area = centered(frame.area(), 70, height.min(frame.area().height));
+                frame.render_widget(Clear, area);
+                frame.render_stateful_widget(
+                    List::new(items.iter().map(String::as_str))
+                        .block(Block::bordered().title(" Copy (enter) "))
+                        .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+                    area,
+                    state,
+                );
+            }
+        }
+    }
+}
+
+fn popup(frame: &mut Frame, paragraph: Paragraph, width: u16, height: u16) {
+    let area = centered(frame.area(), width, height);
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph.block(Block::bordered()), area);
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Links and one-time codes found in the message.
+fn copyable(email: &Email) -> Vec<String> {
+    let text = format!(
+        "{} {} {}",
+        email.subject,
+        email.text_content.as_deref().unwrap_or_default(),
+        email.html_content.as_deref().unwrap_or_default()
+    );
+    let mut items = vec![];
+    for word in text.split(|c: char| c.is_whitespace() || "\"'<>()".contains(c)) {
+        let item = if word.starts_with("http://") || word.starts_with("https://") {
+            word.trim_end_matches(['.', ',', ';'])
+        } else {
+            let code = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+            if !(4..=8).contains(&code.len()) || !code.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            code
+        };
+        if !items.iter().any(|i| i == item) {
+            items.push(item.to_owned());
+        }
+    }
+    items
+}
+
+/// Ask the terminal to copy `text` to the system clipboard (OSC 52).
+fn copy_to_clipboard(text: &str) {
+    let mut stdout = std::io::stdout();
+    let _ = write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text));
+    let _ = stdout.flush();
+}
+
+/// The attachment's ID followed by its file name, without directory components and
+/// reduced to `[A-Za-z0-9._-]`.
+fn file_name(attachment: &Attachment) -> String {
+    let name: String = attachment
+        .filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}", attachment.id, name.trim_start_matches('.'))
+}
+
+/// Write `content` to a new file named `name` in `dir`, adding `-1`, `-2`, … before the
+/// extension instead of overwriting an existing file. Returns the path written.
+fn save(dir: &Path, name: &str, content: &[u8]) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+        _ => (name, String::new()),
+    };
+    let mut path = dir.join(name);
+    for suffix in 1.. {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => return file.write_all(content).map(|()| path),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                path = dir.join(format!("{stem}-{suffix}{extension}"))
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Err(io::ErrorKind::AlreadyExists.into())
+}
+
+fn main() -> Result<(), Error> {
+    let mut interval = Duration::from_secs(10);
+    let mut save_dir = PathBuf::from("tmapi-attachments");
+    let mut clients = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--interval" {
+            let seconds = args.next().ok_or("--interval needs a value")?.parse()?;
+            interval = Duration::from_secs(seconds);
+        } else if arg == "--save-dir" {
+            save_dir = args.next().ok_or("--save-dir needs a value")?.into();
+        } else {
+            clients.push(Client::new(&arg)?);
+        }
+    }
+    if clients.is_empty() {
+        eprintln!("usage: tmapi-tui [--interval <seconds>] [--save-dir <dir>] <address>...");
+        std::process::exit(2);
+    }
+
+    let (updates, updated) = mpsc::channel();
+    let mut app = App {
+        runtime: Runtime::new()?,
+        clients,
+        current: 0,
+        emails: vec![],
+        list: ListState::default(),
+        scroll: 0,
+        attachments: None,
+        mode: Mode::Normal,
+        status: String::new(),
+        interval,
+        save_dir,
+        last_refresh: None,
+        updates,
+        updated,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+    use tmapi::EmailAddress;
+
+    fn email(text: &str) -> Email {
+        Email {
+            id: "abc".into(),
+            from_address: EmailAddress::parse("noreply@example.com").unwrap(),
+            to_address: EmailAddress::parse("y@iusearch.lol").unwrap(),
+            subject: "Your code".into(),
+            received_at: OffsetDateTime::UNIX_EPOCH,
+            html_content: None,
+            text_content: Some(text.into()),
+            has_attachments: false,
+            attachment_count: 0,
+        }
+    }
+
+    #[test]
+    fn finds_links_and_codes() {
+        let email =
+            email("Use 123456 or visit https://example.com/verify?t=1. Order #12 from 2024.");
+        assert_eq!(
+            copyable(&email),
+            ["123456", "https://example.com/verify?t=1", "2024"]
+        );
+    }
+    #[test]
+    fn attachment_file_names_stay_local() {
+        let attachment = Attachment {
+            content_type: "text/plain".into(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            filename: "../../etc/passwd".into(),
+            id: "att_1".into(),
+            size: 0,
+        };
+        assert_eq!(file_name(&attachment), "att_1-passwd");
+    }
+    #[test]
+    fn popups_fit_small_terminals() {
+        let area = Rect::new(0, 0, 20, 5);
+        assert_eq!(centered(area, 60, 12), area);
+        assert_eq!(centered(area, 10, 3), Rect::new(5, 1, 10, 3));
+    }
+    #[test]
+    fn saving_never_overwrites() {
+        let dir = std::env::temp_dir().join(format!("tmapi-tui-{}", std::process::id()));
+        let first = save(&dir, "att_1-report.pdf", b"one").unwrap();
+        let second = save(&dir, "att_1-report.pdf", b"two").unwrap();
+        assert_eq!(first, dir.join("att_1-report.pdf"));
+        assert_eq!(second, dir.join("att_1-report-1.pdf"));
+        assert_eq!(std::fs::read(&first).unwrap(), b"one");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! Delete many messages at once, a few at a time.
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{client::Client, types::Email};
+
+/// How [`Client::delete_many`] and [`Client::delete_matching`] delete messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteOptions {
+    concurrency: usize,
+    attachments: bool,
+}
+
+impl Default for DeleteOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            attachments: false,
+        }
+    }
+}
+
+impl DeleteOptions {
+    /// Up to 8 deletes at a time, attachments are kept.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// How many messages are deleted at the same time, at least 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Delete each message's attachments with [`Client::delete_attachment`] before the message.
+    /// A message is kept when one of its attachments can't be deleted.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::DeleteOptions;
+    ///
+    /// let options = DeleteOptions::new().concurrency(4).attachments(true);
+    /// ```
+    pub fn attachments(mut self, attachments: bool) -> Self {
+        self.attachments = attachments;
+        self
+    }
+}
+
+/// What a bulk delete did, in the order the deletes finished.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeleteReport {
+    /// IDs of the deleted messages
+    pub deleted: Vec<String>,
+    /// Messages that couldn't be deleted
+    pub failed: Vec<DeleteFailure>,
+    /// How many attachments were deleted
+    pub attachments_deleted: usize,
+}
+
+/// A message a bulk delete couldn't delete.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteFailure {
+    pub id: String,
+    pub error: String,
+}
+
+/// What happened to one message, yielded by [`Client::delete_each`] as soon as it's done.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct DeleteOutcome {
+    pub id: String,
+    /// How many of the message's attachments were deleted
+    pub attachments_deleted: usize,
+    /// Why the message couldn't be deleted, `None` when it was
+    pub error: Option<String>,
+}
+
+impl DeleteReport {
+    /// Whether every message was deleted.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Client {
+    /// Delete the messages with these IDs, running up to [`DeleteOptions::concurrency`]
+    /// deletes at a time. Failures don't stop the other deletes, they're in the report.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::{Client, DeleteOptions};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_many.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let report = client
+    ///     .delete_many(["k3u7m0c2x9", "p8d2n5v1q4"], &DeleteOptions::new())
+    ///     .await;
+    /// assert!(report.is_complete());
+    /// assert_eq!(report.deleted.len(), 2);
+    /// # });
+    /// ```
+    pub async fn delete_many<I>(&self, ids: I, options: &DeleteOptions) -> DeleteReport
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut outcomes = self.delete_each(ids, options);
+        let mut report = DeleteReport::default();
+        while let Some(outcome) = outcomes.next().await {
+            report.attachments_deleted += outcome.attachments_deleted;
+            match outcome.error {
+                None => report.deleted.push(outcome.id),
+                Some(error) => report.failed.push(DeleteFailure {
+                    id: outcome.id,
+                    error,
+                }),
+            }
+        }
+        report
+    }
+
+    /// Like [`Client::delete_many`], yielding each message's outcome as soon as it's
+    /// deleted, to report progress.
+    ///
+    /// ## Example
+    /// ```
+    /// use futures_util::StreamExt;
+    /// use tmapi::{Client, DeleteOptions};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_many.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let ids = ["k3u7m0c2x9", "p8d2n5v1q4"];
+    /// let options = DeleteOptions::new();
+    /// let mut outcomes = client.delete_each(ids, &options).enumerate();
+    /// while let Some((done, outcome)) = outcomes.next().await {
+    ///     println!("{}/{}: {} {:?}", done + 1, ids.len(), outcome.id, outcome.error);
+    ///     assert_eq!(outcome.error, None);
+    /// }
+    /// # });
+    /// ```
+    pub fn delete_each<'a, I>(
+        &'a self,
+        ids: I,
+        options: &'a DeleteOptions,
+    ) -> impl Stream<Item = DeleteOutcome> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: Into<String>,
+    {
+        stream::iter(ids)
+            .map(move |id| {
+                let id = id.into();
+                async move {
+                    let (attachments_deleted, result) =
+                        self.delete_one(&id, options.attachments).await;
+                    DeleteOutcome {
+                        id,
+                        attachments_deleted,
+                        error: result.err().map(|error| error.to_string()),
+                    }
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1))
+    }
+
+    /// Delete every message `filter` accepts, see [`Client::delete_many`].
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::{Client, DeleteOptions};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_matching.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let report = client
+    ///     .delete_matching(
+    ///         |email| email.subject.contains("newsletter"),
+    ///         &DeleteOptions::new().attachments(true),
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(report.deleted, ["p8d2n5v1q4"]);
+    /// assert_eq!(report.attachments_deleted, 1);
+    /// # });
+    /// ```
+    pub async fn delete_matching<F>(
+        &self,
+        filter: F,
+        options: &DeleteOptions,
+    ) -> Result<DeleteReport, crate::ErrorType>
+    where
+        F: Fn(&Email) -> bool,
+    {
+        let ids: Vec<String> = self
+            .get_all_emails()
+            .await?
+            .into_iter()
+            .filter(|email| filter(email))
+            .map(|email| email.id)
+            .collect();
+        Ok(self.delete_many(ids, options).await)
+    }
+
+    /// Delete a message and, optionally, its attachments first.
+    /// Returns how many attachments were deleted along with the result.
+    async fn delete_one(
+        &self,
+        id: &str,
+        attachments: bool,
+    ) -> (usize, Result<(), crate::ErrorType>) {
+        let mut deleted = 0;
+        if attachments {
+            let list = match self.get_attachments(id).await {
+                Ok(list) => list,
+                Err(error) => return (deleted, Err(error)),
+            };
+            for attachment in list {
+                if let Err(error) = self.delete_attachment(&attachment.id).await {
+                    return (deleted, Err(error));
+                }
+                deleted += 1;
+            }
+        }
+        (deleted, self.delete_inbox(id).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transport::Method, types::fixtures::FixtureTransport};
+
+    /// Serves fixtures, failing to delete `bad`.
+    fn api() -> FixtureTransport {
+        FixtureTransport::new()
+            .route(
+                Method::Get,
+                "/attachments",
+                "get_inbox_attachments/success.json",
+            )
+            .route(Method::Get, "", "get_emails/success.json")
+            .route(Method::Delete, "/bad", "delete_inbox/error.json")
+            .route(
+                Method::Delete,
+                "/attachments/",
+                "delete_attachment/success.json",
+            )
+            .route(Method::Delete, "", "delete_inbox/success.json")
+    }
+
+    fn client(api: &FixtureTransport) -> Client {
+        Client::new("y@iusearch.lol")
+            .unwrap()
+            .with_transport(api.clone())
+    }
+
+    #[tokio::test]
+    async fn reports_each_id() {
+        let api = api();
+        let mut report = client(&api)
+            .delete_many(["a", "bad", "b"], &DeleteOptions::new().concurrency(2))
+            .await;
+        report.deleted.sort();
+        assert_eq!(report.deleted, ["a", "b"]);
+        assert_eq!(report.failed[0].id, "bad");
+        assert!(!report.is_complete());
+        assert_eq!(report.attachments_deleted, 0);
+        assert_eq!(api.requests().len(), 3);
+    }
+    #[tokio::test]
+    async fn yields_each_outcome() {
+        let api = api();
+        let client = client(&api);
+        let options = DeleteOptions::new().concurrency(1);
+        let outcomes: Vec<DeleteOutcome> =
+            client.delete_each(["a", "bad"], &options).collect().await;
+        assert_eq!(outcomes[0].id, "a");
+        assert_eq!(outcomes[0].error, None);
+        assert_eq!(outcomes[1].id, "bad");
+        assert!(outcomes[1].error.is_some());
+    }
+    #[tokio::test]
+    async fn matching_with_attachments() {
+        let api = api();
+        let report = client(&api)
+            .delete_matching(
+                |email| email.subject.contains("code"),
+                &DeleteOptions::new().attachments(true),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.deleted, ["k3u7m0c2x9"]);
+        assert_eq!(report.attachments_deleted, 1);
+        assert_eq!(
+            api.requests()[1..],
+            [
+                "GET https://api.driftz.net/inbox/k3u7m0c2x9/attachments",
+                "DELETE https://api.driftz.net/attachments/usm2sw0qfv9a5ku9z4xmh8og",
+                "DELETE https://api.driftz.net/inbox/k3u7m0c2x9",
+            ]
+        );
+    }
+}
@@ -0,0 +1,545 @@
+//! Parse calendar invitations (iCalendar, `.ics`) sent as attachments or inline parts.
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime, PrimitiveDateTime};
+
+use crate::{
+    client::Client,
+    types::{Email, timestamp},
+};
+
+/// A `VCALENDAR` object.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Calendar {
+    /// `METHOD`, `None` for calendars that aren't invitations
+    pub method: Option<CalendarMethod>,
+    pub events: Vec<CalendarEvent>,
+}
+
+/// The iTIP method of a [`Calendar`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum CalendarMethod {
+    Publish,
+    Request,
+    Reply,
+    Cancel,
+    /// Any other method
+    Other(String),
+}
+
+/// A `VEVENT`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CalendarEvent {
+    pub uid: Option<String>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: Option<EventTime>,
+    pub end: Option<EventTime>,
+    pub organizer: Option<Attendee>,
+    pub attendees: Vec<Attendee>,
+    /// `STATUS`, like `CONFIRMED` or `CANCELLED`
+    pub status: Option<String>,
+    pub sequence: u32,
+}
+
+/// A `DTSTART` or `DTEND`.
+///
+/// Zones aren't resolved: a time with a `TZID` is kept as the wall-clock time it was written as.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub enum EventTime {
+    /// A time written in UTC, with a trailing `Z`
+    Utc(#[serde(with = "timestamp")] OffsetDateTime),
+    /// A wall-clock time in the `TZID` zone, or in the reader's own zone when there's none
+    Local {
+        #[serde(with = "basic_format::date_time")]
+        time: PrimitiveDateTime,
+        timezone: Option<String>,
+    },
+    /// Only a date, for all-day events
+    Date(#[serde(with = "basic_format::date")] Date),
+}
+
+/// An `ORGANIZER` or `ATTENDEE`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Attendee {
+    /// The address, without `mailto:`
+    pub email: String,
+    /// `CN`
+    pub name: Option<String>,
+    /// `ROLE`, like `REQ-PARTICIPANT`
+    pub role: Option<String>,
+    /// `PARTSTAT`, like `NEEDS-ACTION` or `ACCEPTED`
+    pub status: Option<String>,
+    pub rsvp: bool,
+}
+
+impl EventTime {
+    /// The time as UTC, only known for [`EventTime::Utc`].
+    pub fn utc(&self) -> Option<OffsetDateTime> {
+        match self {
+            Self::Utc(time) => Some(*time),
+            _ => None,
+        }
+    }
+
+    /// The `TZID` parameter of a [`EventTime::Local`] time.
+    pub fn timezone(&self) -> Option<&str> {
+        match self {
+            Self::Local { timezone, .. } => timezone.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether only a date was given.
+    pub fn is_all_day(&self) -> bool {
+        matches!(self, Self::Date(_))
+    }
+}
+
+impl From<String> for CalendarMethod {
+    fn from(value: String) -> Self {
+        match value.to_ascii_uppercase().as_str() {
+            "PUBLISH" => Self::Publish,
+            "REQUEST" => Self::Request,
+            "REPLY" => Self::Reply,
+            "CANCEL" => Self::Cancel,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<CalendarMethod> for String {
+    fn from(value: CalendarMethod) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for CalendarMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Publish => f.write_str("PUBLISH"),
+            Self::Request => f.write_str("REQUEST"),
+            Self::Reply => f.write_str("REPLY"),
+            Self::Cancel => f.write_str("CANCEL"),
+            Self::Other(method) => f.write_str(method),
+        }
+    }
+}
+
+impl Calendar {
+    /// Parse every `VCALENDAR` in `text`, ignoring anything around them.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::{Calendar, CalendarMethod};
+    ///
+    /// let ics = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nBEGIN:VEVENT\r\n\
+    ///            SUMMARY:Standup\r\nDTSTART:20250708T090000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    /// let calendars = Calendar::parse_all(ics);
+    /// assert_eq!(calendars[0].method, Some(CalendarMethod::Request));
+    /// assert_eq!(calendars[0].events[0].summary.as_deref(), Some("Standup"));
+    /// ```
+    pub fn parse_all(text: &str) -> Vec<Self> {
+        let mut calendars = vec![];
+        let mut calendar: Option<Calendar> = None;
+        let mut event: Option<CalendarEvent> = None;
+        // How many components, like `VALARM`, are open inside the event.
+        let mut nested = 0;
+        for line in unfold(text) {
+            let Some(property) = Property::parse(&line) else {
+                continue;
+            };
+            match (
+                property.name.as_str(),
+                property.value.to_ascii_uppercase().as_str(),
+            ) {
+                ("BEGIN", "VCALENDAR") => {
+                    calendar = Some(Calendar {
+                        method: None,
+                        events: vec![],
+                    })
+                }
+                ("END", "VCALENDAR") => calendars.extend(calendar.take()),
+                ("BEGIN", "VEVENT") => {
+                    event = Some(CalendarEvent::default());
+                    nested = 0;
+                }
+                ("BEGIN", _) if event.is_some() => nested += 1,
+                ("END", _) if nested > 0 => nested -= 1,
+                ("END", "VEVENT") => {
+                    if let (Some(calendar), Some(event)) = (&mut calendar, event.take()) {
+                        calendar.events.push(event);
+                    }
+                }
+                ("METHOD", _) => {
+                    if let Some(calendar) = &mut calendar {
+                        calendar.method = Some(property.value.into());
+                    }
+                }
+                _ if nested > 0 => {}
+                _ => {
+                    if let Some(event) = &mut event {
+                        event.set(property);
+                    }
+                }
+            }
+        }
+        calendars
+    }
+}
+
+impl CalendarEvent {
+    fn set(&mut self, property: Property) {
+        match property.name.as_str() {
+            "UID" => self.uid = Some(unescape(&property.value)),
+            "SUMMARY" => self.summary = Some(unescape(&property.value)),
+            "DESCRIPTION" => self.description = Some(unescape(&property.value)),
+            "LOCATION" => self.location = Some(unescape(&property.value)),
+            "STATUS" => self.status = Some(property.value),
+            "SEQUENCE" => self.sequence = property.value.trim().parse().unwrap_or_default(),
+            "DTSTART" => self.start = property.time(),
+            "DTEND" => self.end = property.time(),
+            "ORGANIZER" => self.organizer = Some(property.attendee()),
+            "ATTENDEE" => self.attendees.push(property.attendee()),
+            _ => {}
+        }
+    }
+}
+
+impl Client {
+    /// Every calendar sent with a message: inline in its text body, or as `text/calendar`
+    /// (or `.ics`) attachments, which are downloaded.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::{CalendarMethod, Client};
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// let calendars = client.calendars(&email).await.unwrap();
+    /// assert_eq!(calendars[0].method, Some(CalendarMethod::Request));
+    /// # };
+    /// ```
+    pub async fn calendars(&self, email: &Email) -> Result<Vec<Calendar>, crate::ErrorType> {
+        let mut calendars = Calendar::parse_all(email.text_content.as_deref().unwrap_or_default());
+        if !email.has_attachments {
+            return Ok(calendars);
+        }
+        for attachment in self.get_attachments(&email.id).await? {
+            let content_type = attachment.content_type.to_ascii_lowercase();
+            if content_type.starts_with("text/calendar")
+                || content_type.starts_with("application/ics")
+                || attachment.filename.to_ascii_lowercase().ends_with(".ics")
+            {
+                let content = self.download_attachment(&attachment.id).await?;
+                calendars.extend(Calendar::parse_all(&String::from_utf8_lossy(&content)));
+            }
+        }
+        Ok(calendars)
+    }
+}
+
+/// A content line: `NAME;PARAM=value:VALUE`.
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside of a quoted parameter value.
+        let mut quoted = false;
+        let split = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..split], &line[split + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.trim().to_ascii_uppercase(),
+                    value.trim_matches('"').to_owned(),
+                ))
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_owned(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn time(&self) -> Option<EventTime> {
+        let value = self.value.trim();
+        if self.param("VALUE") == Some("DATE") || value.len() == 8 {
+            return basic_format::parse_date(value).map(EventTime::Date);
+        }
+        let time = basic_format::parse_date_time(value.get(..15)?)?;
+        match &value[15..] {
+            "Z" => Some(EventTime::Utc(time.assume_utc())),
+            "" => Some(EventTime::Local {
+                time,
+                timezone: self.param("TZID").map(String::from),
+            }),
+            _ => None,
+        }
+    }
+
+    fn attendee(&self) -> Attendee {
+        let value = self.value.trim();
+        let email = value
+            .get(..7)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+            .map_or(value, |_| &value[7..]);
+        Attendee {
+            email: email.to_owned(),
+            name: self.param("CN").map(String::from),
+            role: self.param("ROLE").map(String::from),
+            status: self.param("PARTSTAT").map(String::from),
+            rsvp: self
+                .param("RSVP")
+                .is_some_and(|rsvp| rsvp.eq_ignore_ascii_case("TRUE")),
+        }
+    }
+}
+
+/// Join folded lines: a line starting with a space or tab continues the previous one.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// The iCalendar basic formats, `YYYYMMDD` and `YYYYMMDDTHHMMSS`.
+mod basic_format {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    pub(super) fn parse_date(value: &str) -> Option<Date> {
+        Date::from_calendar_date(
+            value.get(0..4)?.parse().ok()?,
+            Month::try_from(value.get(4..6)?.parse::<u8>().ok()?).ok()?,
+            value.get(6..8)?.parse().ok()?,
+        )
+        .ok()
+        .filter(|_| value.len() == 8)
+    }
+
+    pub(super) fn parse_date_time(value: &str) -> Option<PrimitiveDateTime> {
+        let (date, time) = value.split_once('T')?;
+        let time = (time.len() == 6).then_some(time)?;
+        let time = Time::from_hms(
+            time.get(0..2)?.parse().ok()?,
+            time.get(2..4)?.parse().ok()?,
+            time.get(4..6)?.parse().ok()?,
+        )
+        .ok()?;
+        Some(PrimitiveDateTime::new(parse_date(date)?, time))
+    }
+
+    fn format_date(date: Date) -> String {
+        format!(
+            "{:04}{:02}{:02}",
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        )
+    }
+
+    pub(super) mod date {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            date: &Date,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&format_date(*date))
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Date, D::Error> {
+            let value = String::deserialize(deserializer)?;
+            parse_date(&value).ok_or_else(|| D::Error::custom(format!("invalid date {value:?}")))
+        }
+    }
+
+    pub(super) mod date_time {
+        use super::*;
+
+        pub(crate) fn serialize<S: Serializer>(
+            time: &PrimitiveDateTime,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&format!(
+                "{}T{:02}{:02}{:02}",
+                format_date(time.date()),
+                time.hour(),
+                time.minute(),
+                time.second()
+            ))
+        }
+
+        pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<PrimitiveDateTime, D::Error> {
+            let value = String::deserialize(deserializer)?;
+            parse_date_time(&value)
+                .ok_or_else(|| D::Error::custom(format!("invalid date-time {value:?}")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Month, Time};
+
+    const INVITE: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        METHOD:REQUEST\r\n\
+        BEGIN:VTIMEZONE\r\n\
+        TZID:Europe/Berlin\r\n\
+        END:VTIMEZONE\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:42@example.com\r\n\
+        SUMMARY:Quarterly review\\, part 2\r\n\
+        DESCRIPTION:Agenda:\\n1. Numbers\r\n\
+        DTSTART;TZID=Europe/Berlin:20250708T150000\r\n\
+        DTEND:20250708T140000Z\r\n\
+        ORGANIZER;CN=\"Doe, Jane\":mailto:jane@example.com\r\n\
+        ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE;CN=Y:mailto:y@iusea\r\n \
+        rch.lol\r\n\
+        SEQUENCE:1\r\n\
+        BEGIN:VALARM\r\n\
+        ACTION:DISPLAY\r\n\
+        DESCRIPTION:This is an event reminder\r\n\
+        TRIGGER:-P0DT0H15M0S\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    #[test]
+    fn invitation() {
+        let calendars = Calendar::parse_all(INVITE);
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(calendars[0].method, Some(CalendarMethod::Request));
+        let event = &calendars[0].events[0];
+        assert_eq!(event.summary.as_deref(), Some("Quarterly review, part 2"));
+        assert_eq!(event.description.as_deref(), Some("Agenda:\n1. Numbers"));
+        assert_eq!(event.sequence, 1);
+
+        let start = event.start.as_ref().unwrap();
+        assert_eq!(start.timezone(), Some("Europe/Berlin"));
+        assert_eq!(start.utc(), None);
+        let EventTime::Local { time, .. } = start else {
+            panic!("{start:?} isn't a local time");
+        };
+        assert_eq!(time.hour(), 15);
+        assert!(!start.is_all_day());
+        let end = event.end.as_ref().unwrap().utc().unwrap();
+        assert_eq!(end.hour(), 14);
+
+        let organizer = event.organizer.as_ref().unwrap();
+        assert_eq!(organizer.email, "jane@example.com");
+        assert_eq!(organizer.name.as_deref(), Some("Doe, Jane"));
+        assert_eq!(
+            event.attendees,
+            [Attendee {
+                email: "y@iusearch.lol".into(),
+                name: Some("Y".into()),
+                role: Some("REQ-PARTICIPANT".into()),
+                status: Some("NEEDS-ACTION".into()),
+                rsvp: true,
+            }]
+        );
+    }
+    #[test]
+    fn cancellation_and_all_day() {
+        let text = "Cancelled, see below.\n\
+            BEGIN:VCALENDAR\nMETHOD:CANCEL\nBEGIN:VEVENT\nSTATUS:CANCELLED\n\
+            DTSTART;VALUE=DATE:20250709\nEND:VEVENT\nEND:VCALENDAR\n";
+        let calendars = Calendar::parse_all(text);
+        assert_eq!(calendars[0].method, Some(CalendarMethod::Cancel));
+        let event = &calendars[0].events[0];
+        assert_eq!(event.status.as_deref(), Some("CANCELLED"));
+        let start = event.start.as_ref().unwrap();
+        assert!(start.is_all_day());
+        assert_eq!(
+            *start,
+            EventTime::Date(Date::from_calendar_date(2025, Month::July, 9).unwrap())
+        );
+    }
+    #[test]
+    fn local_times_keep_their_zone() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\n\
+            DTSTART;TZID=Europe/Paris:20250101T090000\nDTEND:20250101T100000\n\
+            END:VEVENT\nEND:VCALENDAR\n";
+        let event = &Calendar::parse_all(text)[0].events[0];
+        let nine = PrimitiveDateTime::new(
+            Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+            Time::from_hms(9, 0, 0).unwrap(),
+        );
+        let start = EventTime::Local {
+            time: nine,
+            timezone: Some("Europe/Paris".into()),
+        };
+        assert_eq!(event.start.as_ref(), Some(&start));
+        assert_eq!(event.end.as_ref().unwrap().timezone(), None);
+        let json = serde_json::to_string(&start).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Local":{"time":"20250101T090000","timezone":"Europe/Paris"}}"#
+        );
+        assert_eq!(serde_json::from_str::<EventTime>(&json).unwrap(), start);
+    }
+    #[test]
+    fn malformed_times() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20250708T0\u{e4}000Z\n\
+            DTEND:20250708T1\nEND:VEVENT\nEND:VCALENDAR\n";
+        let event = &Calendar::parse_all(text)[0].events[0];
+        assert_eq!(event.start, None);
+        assert_eq!(event.end, None);
+    }
+}
@@ -1,19 +1,23 @@
-use reqwest::Client as ReqClient;
+use std::sync::Arc;
+
+use crate::{
+    transport::{ReqwestTransport, Transport},
+    types::address::{AddressError, EmailAddress},
+};
 
 pub(crate) const API_URL: &str = "https://api.driftz.net";
 
 #[derive(Debug, Clone)]
 pub struct Client {
     /// The email which the client uses
-    pub email: String,
-    /// A [reqwest] client for communicating with the API
-    ///
-    /// [reqwest]: <https://docs.rs/reqwest>
-    pub client: ReqClient,
+    pub email: EmailAddress,
+    /// The [`Transport`] requests are sent through
+    pub(crate) transport: Arc<dyn Transport>,
+    pub(crate) redact_address: bool,
 }
 impl Client {
     /// Create a new instance of `Client`
-    /// Returns None when the email is invalid.
+    /// Returns an [`AddressError`] when the email is invalid.
     /// Your email must end with one of the supported domains returned by [`Client::get_domains`].
     ///
     /// ## Example
@@ -23,16 +27,49 @@ impl Client {
     /// let client = Client::new("y@iusearch.lol").unwrap();
     /// ```
     /// [`Client::get_domains`]: crate::Client::get_domains
-    pub fn new<S>(email: S) -> Option<Self>
+    /// [`AddressError`]: crate::AddressError
+    pub fn new<S>(email: S) -> Result<Self, AddressError>
+    where
+        S: AsRef<str>,
+    {
+        let email = EmailAddress::parse_bare(email.as_ref())?;
+        Ok(Self {
+            email,
+            transport: Arc::new(ReqwestTransport::new()),
+            redact_address: false,
+        })
+    }
+
+    /// Hide the local part of the client's address in `tracing` spans.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::Client;
+    ///
+    /// let client = Client::new("y@iusearch.lol").unwrap().redact_address(true);
+    /// ```
+    pub fn redact_address(mut self, redact: bool) -> Self {
+        self.redact_address = redact;
+        self
+    }
+
+    /// Send requests through `transport` instead of the default [`ReqwestTransport`].
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::{Client, ReqwestTransport};
+    ///
+    /// let http = reqwest::Client::builder().build().unwrap();
+    /// let client = Client::new("y@iusearch.lol")
+    ///     .unwrap()
+    ///     .with_transport(ReqwestTransport::from(http));
+    /// ```
+    pub fn with_transport<T>(mut self, transport: T) -> Self
     where
-        S: Into<String>,
+        T: Transport + 'static,
     {
-        let email = email.into();
-        if !email_address::EmailAddress::is_valid(&email) {
-            return None;
-        }
-        let client = ReqClient::new();
-        Some(Self { email, client })
+        self.transport = Arc::new(transport);
+        self
     }
 }
 
@@ -44,12 +81,12 @@ mod tests {
     fn valid_email() {
         let correct_email = "y@iusearch.lol";
         let client = Client::new(correct_email);
-        assert!(client.is_some())
+        assert!(client.is_ok())
     }
     #[test]
     fn invalid_email() {
         let incorrect_email = "y";
         let client = Client::new(incorrect_email);
-        assert!(client.is_none())
+        assert_eq!(client.unwrap_err(), AddressError::MissingSeparator)
     }
 }
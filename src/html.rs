@@ -0,0 +1,173 @@
+//! Sanitize HTML bodies before displaying them, and convert them to plain text.
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Email;
+
+/// How [`Email::sanitized_html`] treats remote images.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SanitizeOptions {
+    image_proxy: Option<String>,
+}
+
+impl SanitizeOptions {
+    /// Remote images are removed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Load remote images through a proxy instead of removing them.
+    /// The percent-encoded image URL is appended to `prefix`.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::SanitizeOptions;
+    ///
+    /// let options = SanitizeOptions::new().image_proxy("https://proxy.example.com/image?url=");
+    /// ```
+    pub fn image_proxy(mut self, prefix: impl Into<String>) -> Self {
+        self.image_proxy = Some(prefix.into());
+        self
+    }
+}
+
+impl Email {
+    /// The HTML body without scripts, event handlers, styles or remote resources.
+    /// Layout tags such as tables, lists and headings are kept, links open with
+    /// `rel="noopener noreferrer"`. Embedded (`cid:` and `data:image/`) images are kept.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::{Client, SanitizeOptions};
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// let html = email.sanitized_html(&SanitizeOptions::new());
+    /// # };
+    /// ```
+    pub fn sanitized_html(&self, options: &SanitizeOptions) -> Option<String> {
+        self.html_content
+            .as_deref()
+            .map(|html| sanitize(html, options))
+    }
+
+    /// The text body, or the HTML body converted to text wrapped at `width` columns when
+    /// there is no text body. Links are listed as numbered references, lists keep their
+    /// bullets and numbers.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::Client;
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// println!("{}", email.plain_text(80));
+    /// # };
+    /// ```
+    pub fn plain_text(&self, width: usize) -> String {
+        match (&self.text_content, &self.html_content) {
+            (Some(text), _) if !text.trim().is_empty() => text.clone(),
+            (_, Some(html)) => html2text::from_read(html.as_bytes(), width.max(20))
+                .unwrap_or_else(|_| html.clone()),
+            _ => String::new(),
+        }
+    }
+}
+
+fn sanitize(html: &str, options: &SanitizeOptions) -> String {
+    let proxy = options.image_proxy.clone();
+    ammonia::Builder::default()
+        .add_url_schemes(["cid", "data"])
+        .attribute_filter(move |element, attribute, value| {
+            filter_url(element, attribute, value, proxy.as_deref())
+        })
+        .clean(html)
+        .to_string()
+}
+
+fn filter_url<'a>(
+    element: &str,
+    attribute: &str,
+    value: &'a str,
+    proxy: Option<&str>,
+) -> Option<Cow<'a, str>> {
+    let lower = value.trim().to_ascii_lowercase();
+    match (element, attribute) {
+        ("img", "src") => {
+            if lower.starts_with("cid:") || lower.starts_with("data:image/") {
+                Some(value.into())
+            } else if lower.starts_with("http://")
+                || lower.starts_with("https://")
+                || lower.starts_with("//")
+            {
+                proxy.map(|prefix| format!("{prefix}{}", percent_encode(value.trim())).into())
+            } else {
+                None
+            }
+        }
+        (_, "href") if lower.starts_with("data:") || lower.starts_with("cid:") => None,
+        _ => Some(value.into()),
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_active_content() {
+        let html = r#"<table><tr><td onclick="steal()">Hi<script>alert(1)</script></td></tr></table><img src="https://tracker.example/p.gif"><a href="javascript:alert(1)">x</a>"#;
+        let clean = sanitize(html, &SanitizeOptions::new());
+        assert!(!clean.contains("script"));
+        assert!(!clean.contains("onclick"));
+        assert!(!clean.contains("tracker"));
+        assert!(!clean.contains("javascript"));
+        assert!(clean.contains("<td>Hi</td>"));
+    }
+    #[test]
+    fn proxies_images() {
+        let options = SanitizeOptions::new().image_proxy("https://proxy/?url=");
+        let clean = sanitize(
+            r#"<img src="https://a.example/b.png?c=d"><img src="cid:logo">"#,
+            &options,
+        );
+        assert!(
+            clean.contains(r#"src="https://proxy/?url=https%3A%2F%2Fa.example%2Fb.png%3Fc%3Dd""#)
+        );
+        assert!(clean.contains(r#"src="cid:logo""#));
+    }
+    #[test]
+    fn plain_text_keeps_links_and_lists() {
+        let mut email: Email = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "from_address": "a@barid.site",
+            "to_address": "y@iusearch.lol",
+            "subject": "Hi",
+            "received_at": 1_752_000_000,
+            "html_content": r#"<ul><li>one</li><li>two</li></ul><a href="https://barid.site">site</a>"#,
+            "text_content": null,
+            "has_attachments": false,
+            "attachment_count": 0,
+        }))
+        .unwrap();
+        let text = email.plain_text(80);
+        assert!(text.contains("* one"));
+        assert!(text.contains("https://barid.site"));
+        email.text_content = Some("plain".into());
+        assert_eq!(email.plain_text(80), "plain");
+    }
+}
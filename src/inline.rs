@@ -0,0 +1,338 @@
+//! Resolve `cid:` references in HTML bodies to the attachments they point at.
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::Client,
+    types::{Attachment, Email},
+};
+
+/// Where [`Client::inline_images`] puts the referenced attachments.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub enum InlineImages {
+    /// Embed them in the HTML as `data:` URIs
+    DataUri,
+    /// Save them in this directory, and reference them by `file://` URL
+    Directory(PathBuf),
+}
+
+impl Client {
+    /// The HTML body with every `cid:` reference that matches one of `attachments` replaced,
+    /// so the message renders offline. References without a matching attachment are kept.
+    /// Returns `None` when the message has no HTML body.
+    ///
+    /// Attachments carry no Content-ID, so a reference matches an attachment by ID, by
+    /// filename, or by filename without its extension. The part after `@` is ignored:
+    /// `cid:logo.png@01D9` matches `logo.png`. Only references in attribute values, like
+    /// `src` or `style`, are replaced.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::{Client, InlineImages};
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// let attachments = client.get_attachments(&email.id).await.unwrap();
+    /// let html = client
+    ///     .inline_images(&email, &attachments, &InlineImages::DataUri)
+    ///     .await
+    ///     .unwrap();
+    /// # };
+    /// ```
+    pub async fn inline_images(
+        &self,
+        email: &Email,
+        attachments: &[Attachment],
+        mode: &InlineImages,
+    ) -> Result<Option<String>, crate::ErrorType> {
+        let Some(html) = &email.html_content else {
+            return Ok(None);
+        };
+        let mut resolved = HashMap::new();
+        for cid in content_ids(html) {
+            let Some(attachment) = find_attachment(cid, attachments) else {
+                continue;
+            };
+            if resolved.contains_key(cid) {
+                continue;
+            }
+            let content = self.download_attachment(&attachment.id).await?;
+            let target = match mode {
+                InlineImages::DataUri => format!(
+                    "data:{};base64,{}",
+                    mime_essence(&attachment.content_type),
+                    BASE64_STANDARD.encode(&content)
+                ),
+                InlineImages::Directory(directory) => {
+                    tokio::fs::create_dir_all(directory).await?;
+                    let path = directory.join(file_name(attachment));
+                    tokio::fs::write(&path, &content).await?;
+                    file_url(&path)?
+                }
+            };
+            resolved.insert(cid.to_owned(), target);
+        }
+        Ok(Some(rewrite(html, &resolved)))
+    }
+}
+
+/// The `cid:` references in `html`, without the scheme.
+fn content_ids(html: &str) -> Vec<&str> {
+    references(html)
+        .into_iter()
+        .map(|range| &html[range.start + 4..range.end])
+        .collect()
+}
+
+/// Replace `cid:<id>` with `resolved[id]`, keeping references that aren't in `resolved`.
+fn rewrite(html: &str, resolved: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut copied = 0;
+    for range in references(html) {
+        if let Some(target) = resolved.get(&html[range.start + 4..range.end]) {
+            output.push_str(&html[copied..range.start]);
+            output.push_str(target);
+            copied = range.end;
+        }
+    }
+    output.push_str(&html[copied..]);
+    output
+}
+
+/// Where `cid:<id>` references are in `html`: in attribute values only, at the start of the
+/// value or after a character that can't be part of a word, like `url(`.
+fn references(html: &str) -> Vec<Range<usize>> {
+    let mut references = vec![];
+    for value in attribute_values(html) {
+        let bytes = &html.as_bytes()[value.clone()];
+        let mut index = 0;
+        while index + 4 <= bytes.len() {
+            let at_boundary = index == 0 || !bytes[index - 1].is_ascii_alphanumeric();
+            if !at_boundary || !bytes[index..index + 4].eq_ignore_ascii_case(b"cid:") {
+                index += 1;
+                continue;
+            }
+            let start = value.start + index;
+            let rest = &html[start + 4..value.end];
+            let end = start + 4 + rest.find(is_delimiter).unwrap_or(rest.len());
+            if end > start + 4 {
+                references.push(start..end);
+            }
+            index = end - value.start;
+        }
+    }
+    references
+}
+
+/// The ranges of the attribute values in `html`'s tags, without their quotes.
+/// Text, comments and declarations are skipped.
+fn attribute_values(html: &str) -> Vec<Range<usize>> {
+    let bytes = html.as_bytes();
+    let mut values = vec![];
+    let mut index = 0;
+    while let Some(open) = html[index..].find('<') {
+        index += open + 1;
+        if html[index..].starts_with("!--") {
+            index = html[index..]
+                .find("-->")
+                .map_or(html.len(), |end| index + end + 3);
+            continue;
+        }
+        if !bytes.get(index).is_some_and(u8::is_ascii_alphabetic) {
+            continue;
+        }
+        // Inside a tag: skip the name, then read `name[=value]` pairs up to `>`.
+        while index < bytes.len() && bytes[index] != b'>' {
+            if bytes[index] != b'=' {
+                index += 1;
+                continue;
+            }
+            index += 1;
+            while bytes.get(index).is_some_and(u8::is_ascii_whitespace) {
+                index += 1;
+            }
+            let (start, end) = match bytes.get(index) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = index + 1;
+                    let end = html[start..]
+                        .find(quote as char)
+                        .map_or(html.len(), |end| start + end);
+                    (start, end)
+                }
+                _ => {
+                    let end = html[index..]
+                        .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                        .map_or(html.len(), |end| index + end);
+                    (index, end)
+                }
+            };
+            values.push(start..end);
+            index = (end + usize::from(start != index)).min(html.len());
+        }
+    }
+    values
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "\"'<>()".contains(c)
+}
+
+/// `type/subtype` from a `Content-Type`, lowercased, or `application/octet-stream` when it
+/// isn't made of token characters that are safe inside an HTML attribute.
+fn mime_essence(content_type: &str) -> String {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$+-.^_".contains(c))
+    };
+    match essence.split_once('/') {
+        Some((kind, subtype)) if token(kind) && token(subtype) => essence.to_ascii_lowercase(),
+        _ => "application/octet-stream".to_owned(),
+    }
+}
+
+/// A `file://` URL for `path`, made absolute, with everything but unreserved characters
+/// and separators percent-encoded.
+fn file_url(path: &Path) -> io::Result<String> {
+    let path = std::path::absolute(path)?;
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'\\' if cfg!(windows) => encoded.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    // Windows paths start with a drive letter, `file:///C:/...`.
+    if !encoded.starts_with('/') {
+        encoded.insert(0, '/');
+    }
+    Ok(format!("file://{encoded}"))
+}
+
+fn find_attachment<'a>(cid: &str, attachments: &'a [Attachment]) -> Option<&'a Attachment> {
+    let name = cid.split('@').next().unwrap_or(cid);
+    attachments.iter().find(|attachment| {
+        let stem = attachment
+            .filename
+            .rsplit_once('.')
+            .map_or(attachment.filename.as_str(), |(stem, _)| stem);
+        attachment.id == cid
+            || attachment.id == name
+            || attachment.filename.eq_ignore_ascii_case(name)
+            || stem.eq_ignore_ascii_case(name)
+    })
+}
+
+/// `<id>-<filename>`, keeping only characters that are safe in paths.
+fn file_name(attachment: &Attachment) -> String {
+    let filename: String = attachment
+        .filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}", attachment.id, filename.trim_start_matches('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn attachment(id: &str, filename: &str) -> Attachment {
+        Attachment {
+            content_type: "image/png".into(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            filename: filename.into(),
+            id: id.into(),
+            size: 3,
+        }
+    }
+
+    #[test]
+    fn references() {
+        let html = r#"<img src="cid:logo.png@01D9"><div style="background:url(CID:banner)"></div>"#;
+        assert_eq!(content_ids(html), ["logo.png@01D9", "banner"]);
+    }
+    #[test]
+    fn matching() {
+        let attachments = [attachment("a1", "logo.png"), attachment("a2", "banner.jpg")];
+        assert_eq!(
+            find_attachment("logo.png@01D9", &attachments).unwrap().id,
+            "a1"
+        );
+        assert_eq!(find_attachment("banner", &attachments).unwrap().id, "a2");
+        assert_eq!(find_attachment("a2", &attachments).unwrap().id, "a2");
+        assert!(find_attachment("missing", &attachments).is_none());
+    }
+    #[test]
+    fn rewriting() {
+        let resolved = HashMap::from([("logo".to_owned(), "data:image/png;base64,AAA".to_owned())]);
+        assert_eq!(
+            rewrite(r#"<img src="cid:logo"><img src='cid:other'>"#, &resolved),
+            r#"<img src="data:image/png;base64,AAA"><img src='cid:other'>"#
+        );
+    }
+    #[test]
+    fn only_attribute_values() {
+        let html = r#"<p>acid: cid:logo</p><!-- <img src="cid:logo"> --><img alt=x src=cid:logo>"#;
+        assert_eq!(content_ids(html), ["logo"]);
+        assert_eq!(content_ids(r#"<img src="cid:logo"#), ["logo"]);
+        let resolved = HashMap::from([("logo".to_owned(), "file:///logo.png".to_owned())]);
+        assert_eq!(
+            rewrite(r#"<img title="acid:logo" src="cid:logo">"#, &resolved),
+            r#"<img title="acid:logo" src="file:///logo.png">"#
+        );
+    }
+    #[test]
+    fn mime_types() {
+        assert_eq!(mime_essence("Image/PNG; name=logo.png"), "image/png");
+        assert_eq!(mime_essence("image/svg+xml"), "image/svg+xml");
+        assert_eq!(
+            mime_essence(r#"image/png" onerror="alert(1)"#),
+            "application/octet-stream"
+        );
+        assert_eq!(mime_essence("png"), "application/octet-stream");
+    }
+    #[cfg(unix)]
+    #[test]
+    fn file_urls() {
+        assert_eq!(
+            file_url(Path::new("/tmp/my images/a1-logo.png")).unwrap(),
+            "file:///tmp/my%20images/a1-logo.png"
+        );
+        assert_eq!(
+            file_url(Path::new("/tmp/\"'><")).unwrap(),
+            "file:///tmp/%22%27%3E%3C"
+        );
+    }
+    #[test]
+    fn safe_file_names() {
+        assert_eq!(
+            file_name(&attachment("a1", "../../etc/passwd")),
+            "a1-_.._etc_passwd"
+        );
+    }
+}
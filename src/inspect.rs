@@ -0,0 +1,634 @@
+//! Check what an attachment really contains.
+//!
+//! The type is sniffed from the content's magic bytes and compared with the type the sender
+//! claimed. Common types also get basic metadata, and text where there is some to extract.
+//!
+//! ## Example
+//! ```no_run
+//! use tmapi::Client;
+//! use tmapi::inspect::Metadata;
+//!
+//! # async {
+//! let client = Client::new("y@iusearch.lol").unwrap();
+//! let attachments = client.get_attachments("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+//! let inspection = client.inspect_attachment(&attachments[0]).await.unwrap();
+//! assert!(!inspection.mismatch);
+//! assert!(matches!(inspection.metadata, Metadata::Pdf { .. }));
+//! assert!(inspection.text.unwrap().contains("Total: 42.00"));
+//! # };
+//! ```
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{client::Client, types::Attachment};
+
+/// How many bytes a compressed PDF stream may decompress to, bigger ones are skipped.
+const MAX_STREAM_SIZE: u64 = 4 * 1024 * 1024;
+/// How many bytes all of a PDF's streams may decode to, later streams are skipped.
+const MAX_STREAMS_SIZE: u64 = 16 * 1024 * 1024;
+
+/// What [`inspect`] found out about an attachment.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Inspection {
+    /// The type the sender claimed, [`Attachment::content_type`]
+    pub claimed_type: String,
+    /// The type sniffed from the content, `None` when it wasn't recognised
+    pub detected_type: Option<String>,
+    /// Whether the detected type contradicts the claimed one
+    pub mismatch: bool,
+    /// Hex encoded SHA-256 of the content
+    pub sha256: String,
+    pub size: usize,
+    pub metadata: Metadata,
+    /// Text extracted from the content, for PDFs, text files and calendars
+    pub text: Option<String>,
+}
+
+/// Type specific details of an [`Inspection`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub enum Metadata {
+    Pdf {
+        /// The version from the header, like `1.7`
+        version: String,
+        pages: usize,
+    },
+    Zip {
+        /// The names of the archived files
+        entries: Vec<String>,
+    },
+    Image {
+        /// `png`, `jpeg`, `gif` or `webp`
+        format: String,
+        width: u32,
+        height: u32,
+    },
+    Text {
+        lines: usize,
+    },
+    Calendar {
+        events: usize,
+    },
+    /// Nothing more is known about the content
+    None,
+}
+
+impl Client {
+    /// Download an attachment and [`inspect`] it.
+    pub async fn inspect_attachment(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<Inspection, crate::ErrorType> {
+        let content = self.download_attachment(&attachment.id).await?;
+        Ok(inspect(attachment, &content))
+    }
+}
+
+/// Inspect the `content` of `attachment`.
+pub fn inspect(attachment: &Attachment, content: &[u8]) -> Inspection {
+    let detected_type = sniff(content);
+    let mismatch = detected_type
+        .as_deref()
+        .is_some_and(|detected| !compatible(&attachment.content_type, detected));
+    let (metadata, text) = match detected_type.as_deref() {
+        Some("application/pdf") => pdf(content),
+        Some("application/zip") => (
+            Metadata::Zip {
+                entries: zip_entries(content),
+            },
+            None,
+        ),
+        Some(image) if image.starts_with("image/") => (image_metadata(content), None),
+        Some("text/calendar") => {
+            let text = String::from_utf8_lossy(content).into_owned();
+            let events = text
+                .lines()
+                .filter(|line| line.trim().eq_ignore_ascii_case("BEGIN:VEVENT"))
+                .count();
+            (Metadata::Calendar { events }, Some(text))
+        }
+        Some(text) if text.starts_with("text/") => {
+            let text = String::from_utf8_lossy(content).into_owned();
+            (
+                Metadata::Text {
+                    lines: text.lines().count(),
+                },
+                Some(text),
+            )
+        }
+        _ => (Metadata::None, None),
+    };
+    Inspection {
+        claimed_type: attachment.content_type.clone(),
+        detected_type,
+        mismatch,
+        sha256: hex::encode(Sha256::digest(content)),
+        size: content.len(),
+        metadata,
+        text,
+    }
+}
+
+/// The MIME type matching the content's magic bytes.
+fn sniff(content: &[u8]) -> Option<String> {
+    let detected = if content.starts_with(b"%PDF-") {
+        "application/pdf"
+    } else if content.starts_with(b"PK\x03\x04") || content.starts_with(b"PK\x05\x06") {
+        "application/zip"
+    } else if content.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if content.starts_with(b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if content.starts_with(b"GIF87a") || content.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if content.len() >= 12 && &content[..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+        "image/webp"
+    } else if content.starts_with(b"\x1f\x8b") {
+        "application/gzip"
+    } else {
+        let text = std::str::from_utf8(content)
+            .ok()
+            .filter(|t| !t.contains('\0'))?;
+        let start = text.trim_start_matches('\u{feff}').trim_start();
+        let lower = start.get(..15).unwrap_or(start).to_ascii_lowercase();
+        if lower.starts_with("begin:vcalendar") {
+            "text/calendar"
+        } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+            "text/html"
+        } else {
+            "text/plain"
+        }
+    };
+    Some(detected.to_owned())
+}
+
+/// Whether a `detected` type is a plausible reading of the `claimed` one.
+fn compatible(claimed: &str, detected: &str) -> bool {
+    let claimed = claimed
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if claimed == detected || claimed.is_empty() || claimed == "application/octet-stream" {
+        return true;
+    }
+    match detected {
+        "image/jpeg" => claimed == "image/jpg" || claimed == "image/pjpeg",
+        "application/zip" => {
+            claimed.ends_with("zip")
+                || claimed.starts_with("application/vnd.openxmlformats")
+                || claimed.starts_with("application/vnd.oasis")
+                || claimed == "application/epub+zip"
+                || claimed == "application/java-archive"
+        }
+        "application/gzip" => claimed.contains("gzip") || claimed == "application/x-tar",
+        "text/plain" => {
+            claimed.starts_with("text/")
+                || claimed.ends_with("json")
+                || claimed.ends_with("xml")
+                || claimed == "application/javascript"
+        }
+        "text/html" => claimed == "application/xhtml+xml",
+        "text/calendar" => claimed == "application/ics" || claimed == "text/x-vcalendar",
+        _ => false,
+    }
+}
+
+fn pdf(content: &[u8]) -> (Metadata, Option<String>) {
+    let version = content[5..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .map(|b| *b as char)
+        .collect();
+    let pages = find_all(content, b"/Type")
+        .filter(|&i| {
+            let rest = &content[i + 5..];
+            let rest = &rest[rest.iter().take_while(|b| b.is_ascii_whitespace()).count()..];
+            rest.starts_with(b"/Page") && !rest.starts_with(b"/Pages")
+        })
+        .count();
+    let text: String = pdf_streams(content)
+        .iter()
+        .map(|stream| pdf_text(stream))
+        .collect();
+    let text = Some(text.trim().to_owned()).filter(|text| !text.is_empty());
+    (Metadata::Pdf { version, pages }, text)
+}
+
+/// The decoded content of every stream that is stored raw or with `FlateDecode`, skipping
+/// streams that decompress to more than [`MAX_STREAM_SIZE`] and stopping once
+/// [`MAX_STREAMS_SIZE`] bytes were decoded in total.
+fn pdf_streams(content: &[u8]) -> Vec<Vec<u8>> {
+    let mut streams = vec![];
+    let mut budget = MAX_STREAMS_SIZE;
+    // Where the previous stream ended, nothing before it is looked at again.
+    let mut previous_end = 0;
+    for start in find_all(content, b"stream") {
+        if start < previous_end || content[..start].ends_with(b"end") {
+            continue;
+        }
+        let dictionary_start = content[previous_end..start]
+            .windows(3)
+            .rposition(|w| w == b"obj")
+            .map_or(previous_end, |i| previous_end + i);
+        let mut data_start = start + 6;
+        if content[data_start..].starts_with(b"\r") {
+            data_start += 1;
+        }
+        if content[data_start..].starts_with(b"\n") {
+            data_start += 1;
+        }
+        let Some(length) = find_all(&content[data_start..], b"endstream").next() else {
+            break;
+        };
+        previous_end = data_start + length + b"endstream".len();
+        let data = &content[data_start..data_start + length];
+        let dictionary = &content[dictionary_start..start];
+        if find_all(dictionary, b"/Filter").next().is_none() {
+            budget = budget.saturating_sub(data.len() as u64);
+            streams.push(data.to_vec());
+        } else if find_all(dictionary, b"/FlateDecode").next().is_some() {
+            let mut decoded = vec![];
+            let limit = MAX_STREAM_SIZE.min(budget);
+            let result = flate2::read::ZlibDecoder::new(data)
+                .take(limit + 1)
+                .read_to_end(&mut decoded);
+            budget = budget.saturating_sub(decoded.len() as u64);
+            if result.is_ok() && decoded.len() as u64 <= limit {
+                streams.push(decoded);
+            }
+        }
+        if budget == 0 {
+            break;
+        }
+    }
+    streams
+}
+
+/// The text shown by a content stream's `Tj`, `TJ`, `'` and `"` operators, one line per
+/// positioning operator.
+fn pdf_text(stream: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pending = String::new();
+    let mut i = 0;
+    while i < stream.len() {
+        match stream[i] {
+            b'(' => {
+                let (string, end) = literal_string(stream, i);
+                pending.push_str(&string);
+                i = end;
+            }
+            b'-' | b'0'..=b'9' | b'.' => {
+                let end = stream[i..]
+                    .iter()
+                    .position(|b| !(b.is_ascii_digit() || *b == b'.' || *b == b'-'))
+                    .map_or(stream.len(), |n| i + n);
+                // Wide gaps between the strings of a TJ array separate words.
+                let number = std::str::from_utf8(&stream[i..end]).unwrap_or_default();
+                if number.parse::<f32>().is_ok_and(|n| n < -200.0) && !pending.is_empty() {
+                    pending.push(' ');
+                }
+                i = end.max(i + 1);
+            }
+            b'%' => {
+                i += stream[i..]
+                    .iter()
+                    .position(|b| *b == b'\n' || *b == b'\r')
+                    .unwrap_or(stream.len() - i);
+            }
+            b if b.is_ascii_alphabetic() || b == b'\'' || b == b'"' || b == b'*' => {
+                let end = stream[i..]
+                    .iter()
+                    .position(|b| !(b.is_ascii_alphabetic() || b"'\"*".contains(b)))
+                    .map_or(stream.len(), |n| i + n);
+                match &stream[i..end] {
+                    b"Tj" | b"TJ" => text.push_str(&std::mem::take(&mut pending)),
+                    b"'" | b"\"" => {
+                        text.push('\n');
+                        text.push_str(&std::mem::take(&mut pending));
+                    }
+                    b"Td" | b"TD" | b"T*" | b"Tm" | b"ET" => {
+                        if !text.is_empty() && !text.ends_with('\n') {
+                            text.push('\n');
+                        }
+                    }
+                    _ => pending.clear(),
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Decode the literal string starting at `start`, returning it and the index after it.
+fn literal_string(stream: &[u8], start: usize) -> (String, usize) {
+    let mut string = String::new();
+    let mut depth = 0;
+    let mut i = start;
+    while i < stream.len() {
+        let byte = stream[i];
+        i += 1;
+        match byte {
+            b'(' => {
+                depth += 1;
+                if depth > 1 {
+                    string.push('(');
+                }
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                string.push(')');
+            }
+            b'\\' if i < stream.len() => {
+                let escaped = stream[i];
+                i += 1;
+                match escaped {
+                    b'n' => string.push('\n'),
+                    b'r' => string.push('\r'),
+                    b't' => string.push('\t'),
+                    b'0'..=b'7' => {
+                        let mut value = (escaped - b'0') as u32;
+                        for _ in 0..2 {
+                            match stream.get(i) {
+                                Some(digit @ b'0'..=b'7') => {
+                                    value = value * 8 + (digit - b'0') as u32;
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        string.extend(char::from_u32(value));
+                    }
+                    b'\r' | b'\n' => {}
+                    other => string.push(other as char),
+                }
+            }
+            other => string.push(other as char),
+        }
+    }
+    (string, i)
+}
+
+/// File names from the ZIP central directory.
+fn zip_entries(content: &[u8]) -> Vec<String> {
+    let Some(end) = content
+        .windows(4)
+        .rposition(|window| window == b"PK\x05\x06")
+    else {
+        return vec![];
+    };
+    let (Some(count), Some(mut offset)) = (u16_at(content, end + 10), u32_at(content, end + 16))
+    else {
+        return vec![];
+    };
+    let mut entries = vec![];
+    for _ in 0..count {
+        let offset_usize = offset as usize;
+        if content.get(offset_usize..offset_usize + 4) != Some(b"PK\x01\x02") {
+            break;
+        }
+        let (Some(name), Some(extra), Some(comment)) = (
+            u16_at(content, offset_usize + 28),
+            u16_at(content, offset_usize + 30),
+            u16_at(content, offset_usize + 32),
+        ) else {
+            break;
+        };
+        let name_start = offset_usize + 46;
+        let Some(name_bytes) = content.get(name_start..name_start + name as usize) else {
+            break;
+        };
+        entries.push(String::from_utf8_lossy(name_bytes).into_owned());
+        offset += 46 + name as u32 + extra as u32 + comment as u32;
+    }
+    entries
+}
+
+fn image_metadata(content: &[u8]) -> Metadata {
+    let dimensions = if content.starts_with(b"\x89PNG") {
+        Some(("png", be32(content, 16), be32(content, 20)))
+    } else if content.starts_with(b"GIF") {
+        Some((
+            "gif",
+            u16_at(content, 6).map(u32::from),
+            u16_at(content, 8).map(u32::from),
+        ))
+    } else if content.starts_with(b"\xff\xd8") {
+        jpeg_dimensions(content).map(|(width, height)| ("jpeg", Some(width), Some(height)))
+    } else {
+        webp_dimensions(content).map(|(width, height)| ("webp", Some(width), Some(height)))
+    };
+    match dimensions {
+        Some((format, Some(width), Some(height))) => Metadata::Image {
+            format: format.into(),
+            width,
+            height,
+        },
+        _ => Metadata::None,
+    }
+}
+
+/// Width and height from the first start-of-frame segment.
+fn jpeg_dimensions(content: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    while i + 9 < content.len() {
+        if content[i] != 0xff {
+            i += 1;
+            continue;
+        }
+        let marker = content[i + 1];
+        let length = u16::from_be_bytes([content[i + 2], content[i + 3]]) as usize;
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            let height = u16::from_be_bytes([content[i + 5], content[i + 6]]) as u32;
+            let width = u16::from_be_bytes([content[i + 7], content[i + 8]]) as u32;
+            return Some((width, height));
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+fn webp_dimensions(content: &[u8]) -> Option<(u32, u32)> {
+    let chunk = content.get(12..16)?;
+    let data = content.get(20..30)?;
+    match chunk {
+        b"VP8X" => {
+            let width = u32::from_le_bytes([data[4], data[5], data[6], 0]) + 1;
+            let height = u32::from_le_bytes([data[7], data[8], data[9], 0]) + 1;
+            Some((width, height))
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes([data[6], data[7]]) & 0x3fff;
+            let height = u16::from_le_bytes([data[8], data[9]]) & 0x3fff;
+            Some((width as u32, height as u32))
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        _ => None,
+    }
+}
+
+fn be32(content: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        content.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+fn u16_at(content: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        content.get(at..at + 2)?.try_into().ok()?,
+    ))
+}
+fn u32_at(content: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        content.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+
+fn find_all<'a>(haystack: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == needle)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn attachment(content_type: &str) -> Attachment {
+        Attachment {
+            content_type: content_type.into(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            filename: "file".into(),
+            id: "a1".into(),
+            size: 0,
+        }
+    }
+
+    fn sample_pdf() -> Vec<u8> {
+        flate_pdf(b"BT /F1 12 Tf 72 720 Td (Invoice 17) Tj 0 -14 Td [(Total:) -250 (42.00)] TJ ET")
+    }
+
+    /// A one page PDF with `content` as its compressed content stream.
+    fn flate_pdf(content: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(content).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut pdf = b"%PDF-1.7\n1 0 obj << /Type /Pages /Count 1 >> endobj\n\
+            2 0 obj << /Type /Page /Parent 1 0 R >> endobj\n\
+            3 0 obj << /Length 0 /Filter /FlateDecode >>\nstream\n"
+            .to_vec();
+        pdf.extend(compressed);
+        pdf.extend(b"\nendstream\nendobj\n%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn pdf_inspection() {
+        let inspection = inspect(&attachment("application/pdf"), &sample_pdf());
+        assert!(!inspection.mismatch);
+        assert_eq!(
+            inspection.metadata,
+            Metadata::Pdf {
+                version: "1.7".into(),
+                pages: 1
+            }
+        );
+        assert_eq!(inspection.text.as_deref(), Some("Invoice 17\nTotal: 42.00"));
+        assert_eq!(inspection.sha256.len(), 64);
+    }
+    #[test]
+    fn oversized_streams_are_skipped() {
+        let mut content = b"BT (bomb) Tj ET".to_vec();
+        content.resize(MAX_STREAM_SIZE as usize + 1, b' ');
+        let inspection = inspect(&attachment("application/pdf"), &flate_pdf(&content));
+        assert_eq!(inspection.text, None);
+    }
+    #[test]
+    fn stream_budget_is_shared() {
+        let mut content = b"BT (bomb) Tj ET".to_vec();
+        content.resize(MAX_STREAM_SIZE as usize - 1, b' ');
+        let one = flate_pdf(&content);
+        let stream = &one[one.windows(7).position(|w| w == b"3 0 obj").unwrap()..];
+        let mut pdf = one.clone();
+        for _ in 0..8 {
+            pdf.extend(stream);
+        }
+        let streams = pdf_streams(&pdf);
+        assert_eq!(streams.len(), (MAX_STREAMS_SIZE / MAX_STREAM_SIZE) as usize);
+    }
+    #[test]
+    fn mismatched_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x08\x08\x06\0\0\0";
+        let inspection = inspect(&attachment("application/pdf"), png);
+        assert!(inspection.mismatch);
+        assert_eq!(inspection.detected_type.as_deref(), Some("image/png"));
+        assert_eq!(
+            inspection.metadata,
+            Metadata::Image {
+                format: "png".into(),
+                width: 16,
+                height: 8
+            }
+        );
+    }
+    #[test]
+    fn zip_listing() {
+        // One empty stored file called `a.txt`
+        let mut zip = b"PK\x03\x04".to_vec();
+        zip.extend([0; 22]);
+        zip.extend(5u16.to_le_bytes());
+        zip.extend([0; 2]);
+        zip.extend(b"a.txt");
+        let directory = zip.len() as u32;
+        zip.extend(b"PK\x01\x02");
+        zip.extend([0; 24]);
+        zip.extend(5u16.to_le_bytes());
+        zip.extend([0; 16]);
+        zip.extend(b"a.txt");
+        let size = zip.len() as u32 - directory;
+        zip.extend(b"PK\x05\x06");
+        zip.extend([0; 4]);
+        zip.extend([1, 0, 1, 0]);
+        zip.extend(size.to_le_bytes());
+        zip.extend(directory.to_le_bytes());
+        zip.extend([0; 2]);
+        let inspection = inspect(
+            &attachment("application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+            &zip,
+        );
+        assert!(!inspection.mismatch);
+        assert_eq!(
+            inspection.metadata,
+            Metadata::Zip {
+                entries: vec!["a.txt".into()]
+            }
+        );
+    }
+    #[test]
+    fn text_and_calendar() {
+        let inspection = inspect(&attachment("text/csv; charset=utf-8"), b"a,b\n1,2\n");
+        assert!(!inspection.mismatch);
+        assert_eq!(inspection.metadata, Metadata::Text { lines: 2 });
+        let ics = b"BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let inspection = inspect(&attachment("text/calendar"), ics);
+        assert_eq!(inspection.metadata, Metadata::Calendar { events: 1 });
+    }
+}
@@ -1,24 +1,64 @@
 //! A simple library to help with using [vwh]'s TempMail service on <https://barid.site>
 //!
-//! ```no_run
+//! ```
 //! use tmapi::Client;
-//! # async{
+//! # use tmapi::vcr::{Matching, VcrTransport};
+//! # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_first.json");
+//! # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
 //! let client = Client::new("y@iusearch.lol").unwrap();
+//! # let client = client.with_transport(vcr);
 //! //                              limit, offset
 //! let emails = client.get_emails(  10  ,   0   ).await.unwrap();
 //! let first_email = emails.iter().next().unwrap();
 //! let id = &first_email.id;
 //! client.delete_inbox(id).await.unwrap();
-//! # };
+//! # });
 //! ```
 //! [vwh]: <https://vwh.sh>
+mod bulk_delete;
+#[cfg(feature = "calendar")]
+mod calendar;
 mod client;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "html")]
+mod inline;
+#[cfg(feature = "inspect")]
+pub mod inspect;
+#[cfg(feature = "mime")]
+mod mime;
+mod retention;
+mod telemetry;
+mod temp_inbox;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod thread;
+mod transport;
 mod types;
+#[cfg(feature = "vcr")]
+pub mod vcr;
 
 pub(crate) use types::errors::ErrorType;
 
+pub use bulk_delete::{DeleteFailure, DeleteOptions, DeleteOutcome, DeleteReport};
+#[cfg(feature = "calendar")]
+pub use calendar::{Attendee, Calendar, CalendarEvent, CalendarMethod, EventTime};
 pub use client::Client;
+#[cfg(feature = "html")]
+pub use html::SanitizeOptions;
+#[cfg(feature = "html")]
+pub use inline::InlineImages;
+pub use retention::{RetentionPolicy, RetentionReport, RetentionRun, RetentionTask};
+pub use temp_inbox::TempInbox;
+pub use thread::{Grouping, Thread};
+pub use transport::{HttpRequest, HttpResponse, Method, ReqwestTransport, Transport};
 pub use types::Attachment;
 pub use types::Email;
+pub use types::address::{AddressError, EmailAddress};
 pub use types::errors::EmailsError;
-pub use types::health::{ServerHealth, ServerHealthStatus};
+pub use types::health::{
+    HealthCheck, HealthState, HealthTransition, ServerHealth, ServerHealthStatus,
+};
+pub use types::readiness::{ReadinessCheck, ReadinessReport};
+pub use types::stats::{ArrivalBucket, AttachmentStats, InboxStats};
@@ -0,0 +1,261 @@
+//! Render messages as RFC 5322 / MIME documents, for tools that expect raw mail.
+use base64::{Engine, prelude::BASE64_STANDARD};
+use time::format_description::well_known::Rfc2822;
+
+use crate::{
+    client::Client,
+    types::{Attachment, Email, address::EmailAddress},
+};
+
+const LINE_LENGTH: usize = 76;
+
+impl Email {
+    /// Render the message as an RFC 5322 document.
+    /// The bodies and `attachments` are base64 encoded, so the output is plain ASCII.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::Client;
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// let raw = email.to_rfc5322(&[]);
+    /// # };
+    /// ```
+    pub fn to_rfc5322(&self, attachments: &[(Attachment, Vec<u8>)]) -> String {
+        let mut message = String::new();
+        let date = self.received_at.format(&Rfc2822).unwrap_or_default();
+        header(&mut message, "Date", &date);
+        header(&mut message, "From", &encode_address(&self.from_address));
+        header(&mut message, "To", &encode_address(&self.to_address));
+        header(&mut message, "Subject", &encode_word(&self.subject));
+        header(
+            &mut message,
+            "Message-ID",
+            &format!("<{}@barid.site>", self.id),
+        );
+        header(&mut message, "MIME-Version", "1.0");
+
+        let body = self.render_body();
+        if attachments.is_empty() {
+            message.push_str(&body);
+            return message;
+        }
+        let boundary = format!("tmapi-mixed-{}", self.id);
+        header(
+            &mut message,
+            "Content-Type",
+            &format!("multipart/mixed; boundary=\"{boundary}\""),
+        );
+        message.push_str("\r\n");
+        message.push_str(&format!("--{boundary}\r\n"));
+        message.push_str(&body);
+        for (attachment, content) in attachments {
+            message.push_str(&format!("\r\n--{boundary}\r\n"));
+            header(&mut message, "Content-Type", &attachment.content_type);
+            header(
+                &mut message,
+                "Content-Disposition",
+                &format!("attachment; {}", filename_parameter(&attachment.filename)),
+            );
+            header(&mut message, "Content-Transfer-Encoding", "base64");
+            message.push_str("\r\n");
+            message.push_str(&encode_base64(content));
+        }
+        message.push_str(&format!("\r\n--{boundary}--\r\n"));
+        message
+    }
+
+    /// The body part headers and content, `multipart/alternative` when both formats are present.
+    fn render_body(&self) -> String {
+        let mut body = String::new();
+        match (&self.text_content, &self.html_content) {
+            (Some(text), Some(html)) => {
+                let boundary = format!("tmapi-alternative-{}", self.id);
+                header(
+                    &mut body,
+                    "Content-Type",
+                    &format!("multipart/alternative; boundary=\"{boundary}\""),
+                );
+                body.push_str("\r\n");
+                for (subtype, content) in [("plain", text), ("html", html)] {
+                    body.push_str(&format!("--{boundary}\r\n"));
+                    text_part(&mut body, subtype, content);
+                    body.push_str("\r\n");
+                }
+                body.push_str(&format!("--{boundary}--\r\n"));
+            }
+            (None, Some(html)) => text_part(&mut body, "html", html),
+            (text, None) => text_part(&mut body, "plain", text.as_deref().unwrap_or_default()),
+        }
+        body
+    }
+}
+
+impl Client {
+    /// Fetch a message's attachments and render it as an RFC 5322 document.
+    /// See [`Email::to_rfc5322`].
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::Client;
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let email = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// let raw = client.render_message(&email).await.unwrap();
+    /// # };
+    /// ```
+    pub async fn render_message(&self, email: &Email) -> Result<String, crate::ErrorType> {
+        let mut attachments = vec![];
+        if email.has_attachments {
+            for attachment in self.get_attachments(&email.id).await? {
+                let content = self.download_attachment(&attachment.id).await?;
+                attachments.push((attachment, content));
+            }
+        }
+        Ok(email.to_rfc5322(&attachments))
+    }
+}
+
+fn header(message: &mut String, name: &str, value: &str) {
+    let value = value.replace(['\r', '\n'], " ");
+    message.push_str(&format!("{name}: {value}\r\n"));
+}
+
+fn text_part(message: &mut String, subtype: &str, content: &str) {
+    header(
+        message,
+        "Content-Type",
+        &format!("text/{subtype}; charset=utf-8"),
+    );
+    header(message, "Content-Transfer-Encoding", "base64");
+    message.push_str("\r\n");
+    message.push_str(&encode_base64(content.as_bytes()));
+}
+
+/// Base64 with CRLF every 76 characters, as MIME requires.
+fn encode_base64(content: &[u8]) -> String {
+    let encoded = BASE64_STANDARD.encode(content);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / LINE_LENGTH * 2 + 2);
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        wrapped.push_str(std::str::from_utf8(line).unwrap());
+        wrapped.push_str("\r\n");
+    }
+    wrapped
+}
+
+/// The address with a non-ASCII display name encoded as an RFC 2047 word.
+fn encode_address(address: &EmailAddress) -> String {
+    let raw = address.as_str();
+    if raw.is_ascii() {
+        return raw.to_owned();
+    }
+    match address.display_name() {
+        Some(name) if address.email().is_ascii() => {
+            format!("{} <{}>", encode_word(name), address.email())
+        }
+        _ => encode_word(raw),
+    }
+}
+
+/// RFC 2047 encoded-word for non-ASCII header values.
+fn encode_word(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_owned()
+    } else {
+        format!("=?utf-8?B?{}?=", BASE64_STANDARD.encode(value))
+    }
+}
+
+/// The `filename` parameter as a quoted string, or as an RFC 2231 `filename*` for non-ASCII names,
+/// since encoded-words aren't allowed inside quoted strings.
+fn filename_parameter(filename: &str) -> String {
+    if filename.is_ascii() {
+        let escaped = filename.replace('\\', "\\\\").replace('"', "\\\"");
+        return format!("filename=\"{escaped}\"");
+    }
+    let mut encoded = String::from("filename*=utf-8''");
+    for &byte in filename.as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    fn email() -> Email {
+        Email {
+            id: "abc".into(),
+            from_address: EmailAddress::parse("sender@example.com").unwrap(),
+            to_address: EmailAddress::parse("y@iusearch.lol").unwrap(),
+            subject: "Héllo".into(),
+            received_at: OffsetDateTime::UNIX_EPOCH,
+            html_content: Some("<p>Hi</p>".into()),
+            text_content: Some("Hi".into()),
+            has_attachments: false,
+            attachment_count: 0,
+        }
+    }
+
+    #[test]
+    fn alternative_body() {
+        let message = email().to_rfc5322(&[]);
+        assert!(message.starts_with("Date: Thu, 01 Jan 1970 00:00:00 +0000\r\n"));
+        assert!(message.contains("Subject: =?utf-8?B?SMOpbGxv?=\r\n"));
+        assert!(message.contains(
+            "Content-Type: multipart/alternative; boundary=\"tmapi-alternative-abc\"\r\n"
+        ));
+        assert!(message.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(message.ends_with("--tmapi-alternative-abc--\r\n"));
+    }
+    #[test]
+    fn mixed_with_attachment() {
+        let attachment = Attachment {
+            content_type: "application/pdf".into(),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            filename: "invoice.pdf".into(),
+            id: "att_1".into(),
+            size: 4,
+        };
+        let message = email().to_rfc5322(&[(attachment, b"%PDF".to_vec())]);
+        assert!(message.contains("boundary=\"tmapi-mixed-abc\""));
+        assert!(message.contains("Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n"));
+        assert!(message.contains("JVBERg==\r\n"));
+        assert!(message.ends_with("--tmapi-mixed-abc--\r\n"));
+    }
+    #[test]
+    fn encoded_display_names() {
+        let mut email = email();
+        email.from_address = EmailAddress::parse("Zoë <zoe@example.com>").unwrap();
+        let message = email.to_rfc5322(&[]);
+        assert!(message.is_ascii());
+        assert!(message.contains("From: =?utf-8?B?Wm/Dqw==?= <zoe@example.com>\r\n"));
+        assert!(message.contains("To: y@iusearch.lol\r\n"));
+    }
+    #[test]
+    fn filename_parameters() {
+        assert_eq!(
+            filename_parameter(r#"my "best" \ file.pdf"#),
+            r#"filename="my \"best\" \\ file.pdf""#
+        );
+        assert_eq!(
+            filename_parameter("facture été.pdf"),
+            "filename*=utf-8''facture%20%C3%A9t%C3%A9.pdf"
+        );
+    }
+    #[test]
+    fn base64_lines_are_wrapped() {
+        let encoded = encode_base64(&[0; 100]);
+        assert!(encoded.lines().all(|line| line.len() <= LINE_LENGTH));
+    }
+}
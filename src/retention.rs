@@ -0,0 +1,341 @@
+//! Keep long-lived inboxes small by deleting old messages and attachments in the background.
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{
+    bulk_delete::{DeleteFailure, DeleteOptions, DeleteReport},
+    client::Client,
+    types::{Attachment, Email, timestamp},
+};
+
+/// The shortest [`RetentionPolicy::every`] and [`Client::health_monitor`] interval, shorter ones
+/// are raised to it.
+pub(crate) const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which messages and attachments [`Client::enforce_retention`] deletes.
+/// Without limits, nothing is deleted.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use tmapi::RetentionPolicy;
+///
+/// let policy = RetentionPolicy::new()
+///     .max_age(Duration::from_secs(24 * 60 * 60))
+///     .max_count(500)
+///     .attachment_budget(50 * 1024 * 1024);
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    max_age: Option<Duration>,
+    max_count: Option<usize>,
+    attachment_budget: Option<u64>,
+    interval: Duration,
+    /// Not part of the configuration format, set it with [`RetentionPolicy::delete_options`]
+    #[serde(skip)]
+    delete: DeleteOptions,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: None,
+            max_count: None,
+            attachment_budget: None,
+            interval: Duration::from_secs(60),
+            delete: DeleteOptions::new(),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// No limits, checked every minute.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Delete messages received longer than `max_age` ago.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+    /// Keep only the `max_count` newest messages.
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+    /// Delete the oldest attachments once the kept ones add up to more than `bytes`.
+    pub fn attachment_budget(mut self, bytes: u64) -> Self {
+        self.attachment_budget = Some(bytes);
+        self
+    }
+    /// How often the policy is enforced, every minute by default and at most every second.
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(MIN_INTERVAL);
+        self
+    }
+    /// How messages are deleted, see [`Client::delete_many`].
+    pub fn delete_options(mut self, options: DeleteOptions) -> Self {
+        self.delete = options;
+        self
+    }
+}
+
+/// What one enforcement of a [`RetentionPolicy`] deleted.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RetentionReport {
+    /// Messages that were too old or too many
+    pub messages: DeleteReport,
+    /// IDs of the attachments deleted to fit the budget
+    pub attachments_deleted: Vec<String>,
+    /// Attachments that couldn't be deleted
+    pub attachments_failed: Vec<DeleteFailure>,
+    /// The size of the deleted attachments, in bytes
+    pub bytes_freed: u64,
+}
+
+/// The outcome of one background enforcement, see [`RetentionTask::last_run`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct RetentionRun {
+    #[serde(with = "timestamp")]
+    pub at: OffsetDateTime,
+    /// `None` if listing messages or attachments failed
+    pub report: Option<RetentionReport>,
+    pub error: Option<String>,
+}
+
+/// A running [`Client::enforce_retention`] task, stopped when dropped.
+#[derive(Debug)]
+pub struct RetentionTask {
+    handle: JoinHandle<()>,
+    last_run: Arc<Mutex<Option<RetentionRun>>>,
+}
+
+impl RetentionTask {
+    /// The latest enforcement, `None` until the first one is done.
+    pub fn last_run(&self) -> Option<RetentionRun> {
+        self.last_run
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Stop enforcing the policy. An enforcement in progress is cancelled.
+    pub fn stop(self) {}
+}
+
+impl Drop for RetentionTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl Client {
+    /// Enforce `policy` in a background task, right away and then every
+    /// [`RetentionPolicy::every`], until the returned task is stopped or dropped.
+    ///
+    /// Must be called from a Tokio runtime. Intervals shorter than a second, which can come
+    /// from a deserialized policy, are raised to a second.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use tmapi::{Client, RetentionPolicy};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/apply_retention.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let policy = RetentionPolicy::new()
+    ///     .max_count(1)
+    ///     .every(Duration::from_secs(60 * 60));
+    /// let retention = client.enforce_retention(policy);
+    /// // The first enforcement starts right away.
+    /// # while retention.last_run().is_none() {
+    /// #     tokio::time::sleep(Duration::from_millis(10)).await;
+    /// # }
+    /// let run = retention.last_run().unwrap();
+    /// assert_eq!(run.report.unwrap().messages.deleted, ["k3u7m0c2x9"]);
+    /// retention.stop();
+    /// # });
+    /// ```
+    pub fn enforce_retention(&self, policy: RetentionPolicy) -> RetentionTask {
+        let client = self.clone();
+        let last_run = Arc::new(Mutex::new(None));
+        let shared = last_run.clone();
+        let mut ticker = tokio::time::interval(policy.interval.max(MIN_INTERVAL));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let handle = tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                let result = client.apply_retention(&policy).await;
+                let run = RetentionRun {
+                    at: OffsetDateTime::now_utc(),
+                    error: result.as_ref().err().map(ToString::to_string),
+                    report: result.ok(),
+                };
+                if let Some(error) = &run.error {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(address = %client.email, %error, "retention failed");
+                    #[cfg(not(feature = "tracing"))]
+                    eprintln!("tmapi: retention failed for {}: {error}", client.email);
+                }
+                *shared
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(run);
+            }
+        });
+        RetentionTask { handle, last_run }
+    }
+
+    /// Enforce `policy` once: delete expired and excess messages, then the oldest
+    /// attachments over the budget.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::{Client, RetentionPolicy};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/apply_retention.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let report = client
+    ///     .apply_retention(&RetentionPolicy::new().max_count(1))
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(report.messages.deleted, ["k3u7m0c2x9"]);
+    /// # });
+    /// ```
+    pub async fn apply_retention(
+        &self,
+        policy: &RetentionPolicy,
+    ) -> Result<RetentionReport, crate::ErrorType> {
+        let mut report = RetentionReport::default();
+        if policy.max_age.is_some() || policy.max_count.is_some() {
+            let emails = self.get_all_emails().await?;
+            let expired = expired(emails, policy, OffsetDateTime::now_utc());
+            report.messages = self.delete_many(expired, &policy.delete).await;
+        }
+        if let Some(budget) = policy.attachment_budget {
+            let attachments = self.get_all_email_attachments().await?;
+            for attachment in over_budget(attachments, budget) {
+                match self.delete_attachment(&attachment.id).await {
+                    Ok(()) => {
+                        report.bytes_freed += attachment.size;
+                        report.attachments_deleted.push(attachment.id);
+                    }
+                    Err(error) => report.attachments_failed.push(DeleteFailure {
+                        id: attachment.id,
+                        error: error.to_string(),
+                    }),
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// IDs of the messages older than the max age, or past the max count, newest first.
+fn expired(mut emails: Vec<Email>, policy: &RetentionPolicy, now: OffsetDateTime) -> Vec<String> {
+    emails.sort_by_key(|email| std::cmp::Reverse(email.received_at));
+    emails
+        .into_iter()
+        .enumerate()
+        .filter(|(index, email)| {
+            policy
+                .max_age
+                .is_some_and(|age| now - email.received_at > age)
+                || policy.max_count.is_some_and(|count| *index >= count)
+        })
+        .map(|(_, email)| email.id)
+        .collect()
+}
+
+/// The oldest attachments that don't fit in `budget` bytes once the newest are kept.
+fn over_budget(mut attachments: Vec<Attachment>, budget: u64) -> Vec<Attachment> {
+    attachments.sort_by_key(|attachment| std::cmp::Reverse(attachment.created_at));
+    let mut used = 0u64;
+    attachments
+        .into_iter()
+        .filter(|attachment| {
+            used = used.saturating_add(attachment.size);
+            used > budget
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::sample_email;
+
+    fn email(id: &str, received_at: i64) -> Email {
+        Email {
+            id: id.into(),
+            received_at: OffsetDateTime::from_unix_timestamp(received_at).unwrap(),
+            ..sample_email()
+        }
+    }
+
+    fn attachment(id: &str, created_at: i64, size: u64) -> Attachment {
+        Attachment {
+            content_type: "application/pdf".into(),
+            created_at: OffsetDateTime::from_unix_timestamp(created_at).unwrap(),
+            filename: format!("{id}.pdf"),
+            id: id.into(),
+            size,
+        }
+    }
+
+    #[test]
+    fn expiry() {
+        let now = OffsetDateTime::from_unix_timestamp(1_752_010_000).unwrap();
+        let emails = vec![
+            email("old", 1_752_000_000),
+            email("new", 1_752_009_000),
+            email("newer", 1_752_009_500),
+            email("newest", 1_752_009_900),
+        ];
+        let policy = RetentionPolicy::new().max_age(Duration::from_secs(60 * 60));
+        assert_eq!(expired(emails.clone(), &policy, now), ["old"]);
+        let policy = policy.max_count(2);
+        assert_eq!(expired(emails.clone(), &policy, now), ["new", "old"]);
+        assert!(expired(emails, &RetentionPolicy::new(), now).is_empty());
+    }
+    #[test]
+    fn budget() {
+        let attachments = vec![
+            attachment("a", 1, 400),
+            attachment("b", 2, 400),
+            attachment("c", 3, 400),
+        ];
+        let ids: Vec<String> = over_budget(attachments, 900)
+            .into_iter()
+            .map(|attachment| attachment.id)
+            .collect();
+        assert_eq!(ids, ["a"]);
+    }
+    #[tokio::test]
+    async fn zero_interval() {
+        let policy: RetentionPolicy = serde_json::from_value(serde_json::json!({
+            "max_age": null,
+            "max_count": null,
+            "attachment_budget": null,
+            "interval": {"secs": 0, "nanos": 0},
+        }))
+        .unwrap();
+        let client = Client::new("y@iusearch.lol").unwrap();
+        client.enforce_retention(policy).stop();
+        let policy = RetentionPolicy::new().every(Duration::ZERO);
+        assert_eq!(policy.interval, MIN_INTERVAL);
+    }
+}
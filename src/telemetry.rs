@@ -0,0 +1,128 @@
+//! Optional `tracing` spans and `metrics` for every API call.
+//!
+//! Spans are named `tmapi` and carry the endpoint, the client's address, the HTTP status code
+//! and the latency in milliseconds. The client doesn't retry, each span is a single request.
+//!
+//! Metrics:
+//! - `tmapi_requests_total` (counter, by `endpoint`)
+//! - `tmapi_request_duration_seconds` (histogram, by `endpoint`)
+//! - `tmapi_errors_total` (counter, by `endpoint` and `kind`, the [`EmailsError`] variant or `transport`)
+//! - `tmapi_downloaded_bytes_total` (counter)
+//!
+//! [`EmailsError`]: crate::EmailsError
+use std::future::Future;
+
+use crate::{
+    client::Client,
+    transport::{HttpRequest, HttpResponse},
+};
+
+impl Client {
+    /// Run an API call, reporting it when the `tracing` or `metrics` features are enabled.
+    pub(crate) async fn instrument<T, F>(
+        &self,
+        #[cfg_attr(
+            not(any(feature = "tracing", feature = "metrics")),
+            allow(unused_variables)
+        )]
+        endpoint: &'static str,
+        call: F,
+    ) -> Result<T, crate::ErrorType>
+    where
+        F: Future<Output = Result<T, crate::ErrorType>>,
+    {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let start = std::time::Instant::now();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "tmapi",
+            endpoint,
+            address = %self.address_for_logs(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let call = tracing::Instrument::instrument(call, span.clone());
+
+        let result = call.await;
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("latency_ms", start.elapsed().as_millis() as u64);
+            if let Err(error) = &result {
+                tracing::warn!(parent: &span, %error, "request failed");
+            }
+        }
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("tmapi_requests_total", "endpoint" => endpoint).increment(1);
+            metrics::histogram!("tmapi_request_duration_seconds", "endpoint" => endpoint)
+                .record(start.elapsed().as_secs_f64());
+            if let Err(error) = &result {
+                metrics::counter!(
+                    "tmapi_errors_total",
+                    "endpoint" => endpoint,
+                    "kind" => error_kind(error.as_ref()),
+                )
+                .increment(1);
+            }
+        }
+        result
+    }
+
+    /// Send a request, recording its status code on the current span.
+    pub(crate) async fn send(
+        &self,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, crate::ErrorType> {
+        let response = self.transport.send(request).await?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("status", response.status);
+        Ok(response)
+    }
+
+    /// The address as it should appear in logs, see [`Client::redact_address`].
+    #[cfg(feature = "tracing")]
+    fn address_for_logs(&self) -> String {
+        if self.redact_address {
+            format!("***@{}", self.email.domain())
+        } else {
+            self.email.to_string()
+        }
+    }
+}
+
+/// Count bytes received from attachment downloads.
+pub(crate) fn record_download(_bytes: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("tmapi_downloaded_bytes_total").increment(_bytes as u64);
+}
+
+#[cfg(feature = "metrics")]
+fn error_kind(error: &(dyn std::error::Error + Send + Sync + 'static)) -> &'static str {
+    use crate::types::errors::EmailsError;
+
+    match error.downcast_ref::<EmailsError>() {
+        Some(EmailsError::ValidationError { .. }) => "ValidationError",
+        Some(EmailsError::DomainError { .. }) => "DomainError",
+        Some(EmailsError::NotFoundError { .. }) => "NotFoundError",
+        Some(EmailsError::RateLimitError { .. }) => "RateLimitError",
+        Some(EmailsError::InternalError { .. }) => "InternalError",
+        Some(EmailsError::UnknownError { .. }) => "UnknownError",
+        None => "transport",
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_address() {
+        let client = Client::new("y@iusearch.lol").unwrap();
+        assert_eq!(client.address_for_logs(), "y@iusearch.lol");
+        let client = client.redact_address(true);
+        assert_eq!(client.address_for_logs(), "***@iusearch.lol");
+    }
+}
@@ -0,0 +1,222 @@
+//! Throwaway inboxes that clean up after themselves.
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::Deref,
+    time::SystemTime,
+};
+
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::{client::Client, types::address::EmailAddress};
+
+/// Length of the generated local part.
+const LOCAL_PART_LENGTH: usize = 16;
+
+/// A [`Client`] on a freshly generated address, see [`Client::temp_inbox`].
+///
+/// Every message and attachment is deleted when the inbox is dropped or [closed].
+/// Inboxes that can't be cleaned up are logged as leaked, through `tracing` when the
+/// feature is enabled and to stderr otherwise.
+///
+/// Dropping blocks until the cleanup is done on multi-threaded runtimes and outside of
+/// any runtime. On current-thread runtimes, like the default `#[tokio::test]`, the cleanup
+/// is spawned and may not finish before the runtime shuts down, the inbox is logged as
+/// leaked then: [close] the inbox instead.
+///
+/// [closed]: TempInbox::close
+/// [close]: TempInbox::close
+#[derive(Debug)]
+pub struct TempInbox {
+    client: Client,
+    closed: bool,
+}
+
+impl Client {
+    /// A new inbox on a random address, on one of the domains returned by
+    /// [`Client::get_domains`]. It shares this client's transport and settings.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use tmapi::Client;
+    ///
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let inbox = client.temp_inbox().await.unwrap();
+    /// println!("send it to {}", inbox.email);
+    /// let emails = inbox.get_all_emails().await.unwrap();
+    /// inbox.close().await.unwrap();
+    /// # };
+    /// ```
+    pub async fn temp_inbox(&self) -> Result<TempInbox, crate::ErrorType> {
+        let domains = self.get_domains().await?;
+        if domains.is_empty() {
+            return Err("the API supports no domains".into());
+        }
+        let domain = &domains[(random() % domains.len() as u64) as usize];
+        let local_part: String = (0..LOCAL_PART_LENGTH)
+            .map(|_| char::from_digit((random() % 36) as u32, 36).unwrap_or('0'))
+            .collect();
+        let mut client = self.clone();
+        client.email = EmailAddress::parse_bare(&format!("{local_part}@{domain}"))?;
+        Ok(TempInbox {
+            client,
+            closed: false,
+        })
+    }
+}
+
+impl TempInbox {
+    /// Delete every message and attachment now, reporting failures instead of only
+    /// logging them.
+    pub async fn close(mut self) -> Result<(), crate::ErrorType> {
+        self.closed = true;
+        let result = purge(&self.client).await;
+        if let Err(error) = &result {
+            leaked(&self.client, error);
+        }
+        result
+    }
+}
+
+impl Deref for TempInbox {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Drop for TempInbox {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let client = self.client.clone();
+        let pending = PendingCleanup(Some(self.client.clone()));
+        let cleanup = async move {
+            if let Err(error) = purge(&client).await {
+                leaked(&client, &error);
+            }
+            pending.done();
+        };
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| handle.block_on(cleanup))
+            }
+            Ok(handle) => drop(handle.spawn(cleanup)),
+            Err(_) => match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime.block_on(cleanup),
+                Err(error) => leaked(&self.client, &error.into()),
+            },
+        }
+    }
+}
+
+/// Logs the inbox as leaked when dropped before [`PendingCleanup::done`], which happens
+/// when the runtime shuts down before a spawned cleanup finished.
+struct PendingCleanup(Option<Client>);
+
+impl PendingCleanup {
+    fn done(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for PendingCleanup {
+    fn drop(&mut self) {
+        if let Some(client) = self.0.take() {
+            let error = "the runtime shut down before the cleanup finished".into();
+            leaked(&client, &error);
+        }
+    }
+}
+
+/// Delete every attachment, then every message.
+async fn purge(client: &Client) -> Result<(), crate::ErrorType> {
+    for attachment in client.get_all_email_attachments().await? {
+        client.delete_attachment(&attachment.id).await?;
+    }
+    client.delete_all_emails().await?;
+    Ok(())
+}
+
+fn leaked(client: &Client, error: &crate::ErrorType) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(address = %client.email, %error, "leaked temporary inbox");
+    #[cfg(not(feature = "tracing"))]
+    eprintln!("tmapi: leaked temporary inbox {}: {error}", client.email);
+}
+
+/// Not cryptographically secure, only unlikely to repeat.
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transport::Method, types::fixtures::FixtureTransport};
+
+    fn api() -> FixtureTransport {
+        FixtureTransport::new()
+            .route(Method::Get, "/domains", "get_domains/success.json")
+            .route(
+                Method::Get,
+                "/attachments",
+                "get_email_attachments/success.json",
+            )
+            .route(
+                Method::Delete,
+                "/attachments/",
+                "delete_attachment/success.json",
+            )
+            .route(Method::Delete, "/emails/", "delete_emails/success.json")
+    }
+
+    fn cleanup_requests(address: &str) -> Vec<String> {
+        vec![
+            "GET https://api.driftz.net/domains".to_owned(),
+            format!("GET https://api.driftz.net/emails/{address}/attachments?limit=100&offset=0"),
+            "DELETE https://api.driftz.net/attachments/usm2sw0qfv9a5ku9z4xmh8og".to_owned(),
+            format!("DELETE https://api.driftz.net/emails/{address}"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn close() {
+        let api = api();
+        let client = Client::new("y@iusearch.lol")
+            .unwrap()
+            .with_transport(api.clone());
+        let inbox = client.temp_inbox().await.unwrap();
+        let address = inbox.email.to_string();
+        assert!(["barid.site", "iusearch.lol"].contains(&inbox.email.domain()));
+        assert_eq!(inbox.email.local_part().len(), LOCAL_PART_LENGTH);
+        let other = client.temp_inbox().await.unwrap();
+        assert_ne!(inbox.email, other.email);
+        other.close().await.unwrap();
+        api.clear();
+
+        inbox.close().await.unwrap();
+        assert_eq!(api.requests(), cleanup_requests(&address)[1..]);
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn drop_cleans_up() {
+        let api = api();
+        let client = Client::new("y@iusearch.lol")
+            .unwrap()
+            .with_transport(api.clone());
+        let inbox = client.temp_inbox().await.unwrap();
+        let address = inbox.email.to_string();
+        drop(inbox);
+        assert_eq!(api.requests(), cleanup_requests(&address));
+    }
+}
@@ -0,0 +1,368 @@
+//! Fluent expectations on the messages an inbox receives, for end-to-end tests.
+//!
+//! ```
+//! use std::time::Duration;
+//! use tmapi::Client;
+//!
+//! # use tmapi::vcr::{Matching, VcrTransport};
+//! # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/expect.json");
+//! # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let client = Client::new("y@iusearch.lol").unwrap();
+//! # let client = client.with_transport(vcr);
+//! let email = client
+//!     .expect()
+//!     .from("noreply@example.com")
+//!     .subject_contains("Welcome")
+//!     .has_attachment("invoice.pdf")
+//!     .within(Duration::from_secs(30))
+//!     .await
+//!     .unwrap();
+//! assert_eq!(email.id, "k3u7m0c2x9");
+//! # });
+//! ```
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    future::{Future, IntoFuture},
+    pin::Pin,
+    time::Duration,
+};
+
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+use crate::{
+    client::Client,
+    types::{Email, errors::EmailsError},
+};
+
+/// A message the inbox should receive, see [`Client::expect`].
+///
+/// Awaiting it polls the inbox until a message matches every condition, or fails with an
+/// [`ExpectationError`] listing what was received instead.
+#[derive(Debug, Clone)]
+#[must_use = "expectations do nothing unless awaited"]
+pub struct Expectation {
+    client: Client,
+    conditions: Vec<Condition>,
+    since: Option<OffsetDateTime>,
+    within: Duration,
+    poll_every: Duration,
+    cleanup: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    From(String),
+    SubjectContains(String),
+    BodyContains(String),
+    HasAttachment(String),
+}
+
+/// Why an [`Expectation`] failed.
+#[derive(Debug, Error)]
+pub enum ExpectationError {
+    /// No message matched in time
+    #[error("{}", timeout_message(expected, within, received, last_error.as_deref()))]
+    Timeout {
+        /// The conditions, as a sentence
+        expected: String,
+        within: Duration,
+        /// Every message seen during the last poll, with why it didn't match
+        received: Vec<Mismatch>,
+        /// The last error polling ran into, if any
+        last_error: Option<String>,
+    },
+    /// The API failed in a way that retrying won't fix
+    #[error("{0}")]
+    Api(crate::ErrorType),
+}
+
+/// A received message that didn't match an [`Expectation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub email: Email,
+    /// The first condition it failed, described by what the message has instead
+    pub reason: String,
+}
+
+impl Client {
+    /// Expect a message. By default any message received within 30 seconds matches,
+    /// add conditions with the [`Expectation`] methods.
+    pub fn expect(&self) -> Expectation {
+        Expectation {
+            client: self.clone(),
+            conditions: vec![],
+            since: None,
+            within: Duration::from_secs(30),
+            poll_every: Duration::from_secs(2),
+            cleanup: false,
+        }
+    }
+}
+
+impl Expectation {
+    /// Sent from `address`, compared case-insensitively and without the display name.
+    pub fn from(mut self, address: impl Into<String>) -> Self {
+        self.conditions.push(Condition::From(address.into()));
+        self
+    }
+    /// The subject contains `text`.
+    pub fn subject_contains(mut self, text: impl Into<String>) -> Self {
+        self.conditions
+            .push(Condition::SubjectContains(text.into()));
+        self
+    }
+    /// The text or HTML body contains `text`.
+    pub fn body_contains(mut self, text: impl Into<String>) -> Self {
+        self.conditions.push(Condition::BodyContains(text.into()));
+        self
+    }
+    /// Has an attachment named `filename`, compared case-insensitively.
+    pub fn has_attachment(mut self, filename: impl Into<String>) -> Self {
+        self.conditions
+            .push(Condition::HasAttachment(filename.into()));
+        self
+    }
+    /// Ignore messages received before `since`.
+    pub fn since(mut self, since: OffsetDateTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+    /// How long to wait for the message, 30 seconds by default.
+    pub fn within(mut self, timeout: Duration) -> Self {
+        self.within = timeout;
+        self
+    }
+    /// How often to poll the inbox, every 2 seconds by default.
+    pub fn poll_every(mut self, interval: Duration) -> Self {
+        self.poll_every = interval;
+        self
+    }
+    /// Delete the matching message with [`Client::delete_inbox`] once it's found.
+    pub fn cleanup(mut self, cleanup: bool) -> Self {
+        self.cleanup = cleanup;
+        self
+    }
+
+    async fn wait(self) -> Result<Email, ExpectationError> {
+        let deadline = Instant::now() + self.within;
+        // Attachment names by message ID, messages don't change once received.
+        let mut attachments: HashMap<String, Vec<String>> = HashMap::new();
+        let mut received = vec![];
+        let mut last_error = None;
+        loop {
+            match self.poll(&mut attachments).await {
+                Ok(Ok(email)) => {
+                    if self.cleanup {
+                        self.client
+                            .delete_inbox(&email.id)
+                            .await
+                            .map_err(ExpectationError::Api)?;
+                    }
+                    return Ok(email);
+                }
+                Ok(Err(mismatches)) => received = mismatches,
+                Err(error) if is_transient(&error) => last_error = Some(error.to_string()),
+                Err(error) => return Err(ExpectationError::Api(error)),
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ExpectationError::Timeout {
+                    expected: self.describe(),
+                    within: self.within,
+                    received,
+                    last_error,
+                });
+            }
+            tokio::time::sleep(self.poll_every.min(deadline - now)).await;
+        }
+    }
+
+    /// The first matching message, or why each received message didn't match.
+    async fn poll(
+        &self,
+        attachments: &mut HashMap<String, Vec<String>>,
+    ) -> Result<Result<Email, Vec<Mismatch>>, crate::ErrorType> {
+        let mut emails = self.client.get_all_emails().await?;
+        if let Some(since) = self.since {
+            emails.retain(|email| email.received_at >= since);
+        }
+        emails.sort_by_key(|email| email.received_at);
+        let mut mismatches = vec![];
+        for email in emails {
+            let mut reason = None;
+            for condition in &self.conditions {
+                reason = condition.check(&email, &self.client, attachments).await?;
+                if reason.is_some() {
+                    break;
+                }
+            }
+            match reason {
+                None => return Ok(Ok(email)),
+                Some(reason) => mismatches.push(Mismatch { email, reason }),
+            }
+        }
+        Ok(Err(mismatches))
+    }
+
+    fn describe(&self) -> String {
+        let conditions: Vec<String> = self.conditions.iter().map(Condition::describe).collect();
+        if conditions.is_empty() {
+            "a message".to_owned()
+        } else {
+            format!("a message {}", conditions.join(" and "))
+        }
+    }
+}
+
+impl IntoFuture for Expectation {
+    type Output = Result<Email, ExpectationError>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.wait())
+    }
+}
+
+impl Condition {
+    /// `None` when `email` passes, or what it has instead.
+    async fn check(
+        &self,
+        email: &Email,
+        client: &Client,
+        attachments: &mut HashMap<String, Vec<String>>,
+    ) -> Result<Option<String>, crate::ErrorType> {
+        let reason = match self {
+            Self::From(address) => {
+                let sender = email.from_address.email();
+                (!sender.eq_ignore_ascii_case(address)).then(|| format!("sent from {sender}"))
+            }
+            Self::SubjectContains(text) => (!email.subject.contains(text.as_str()))
+                .then(|| format!("subject is {:?}", email.subject)),
+            Self::BodyContains(text) => {
+                let found = [&email.text_content, &email.html_content]
+                    .into_iter()
+                    .flatten()
+                    .any(|body| body.contains(text.as_str()));
+                (!found).then(|| format!("body doesn't contain {text:?}"))
+            }
+            Self::HasAttachment(filename) => {
+                if !email.has_attachments {
+                    return Ok(Some("has no attachments".to_owned()));
+                }
+                if !attachments.contains_key(&email.id) {
+                    let names = client
+                        .get_attachments(&email.id)
+                        .await?
+                        .into_iter()
+                        .map(|attachment| attachment.filename)
+                        .collect();
+                    attachments.insert(email.id.clone(), names);
+                }
+                let names = &attachments[&email.id];
+                (!names.iter().any(|name| name.eq_ignore_ascii_case(filename)))
+                    .then(|| format!("has attachments {names:?}"))
+            }
+        };
+        Ok(reason)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::From(address) => format!("from {address}"),
+            Self::SubjectContains(text) => format!("with a subject containing {text:?}"),
+            Self::BodyContains(text) => format!("with a body containing {text:?}"),
+            Self::HasAttachment(filename) => format!("with an attachment named {filename:?}"),
+        }
+    }
+}
+
+/// Network errors and retryable API errors are worth polling through.
+fn is_transient(error: &crate::ErrorType) -> bool {
+    error
+        .downcast_ref::<EmailsError>()
+        .is_none_or(EmailsError::is_retryable)
+}
+
+fn timeout_message(
+    expected: &str,
+    within: &Duration,
+    received: &[Mismatch],
+    last_error: Option<&str>,
+) -> String {
+    let mut message = format!("expected {expected} within {within:?}");
+    match received.len() {
+        0 => message.push_str(", received nothing"),
+        n => {
+            let _ = write!(message, ", received {n}:");
+            for Mismatch { email, reason } in received {
+                let _ = write!(
+                    message,
+                    "\n  - {:?} from {} ({}): {reason}",
+                    email.subject,
+                    email.from_address.email(),
+                    email.id
+                );
+            }
+        }
+    }
+    if let Some(error) = last_error {
+        let _ = write!(message, "\nlast error: {error}");
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transport::Method, types::fixtures::FixtureTransport};
+
+    fn client() -> Client {
+        let inbox = FixtureTransport::new()
+            .route(
+                Method::Get,
+                "/attachments",
+                "get_inbox_attachments/success.json",
+            )
+            .route(Method::Get, "", "get_emails/success.json");
+        Client::new("y@iusearch.lol").unwrap().with_transport(inbox)
+    }
+
+    #[tokio::test]
+    async fn matches() {
+        let email = client()
+            .expect()
+            .from("SENDER@example.com")
+            .subject_contains("code")
+            .body_contains("<b>123456</b>")
+            .has_attachment("invoice.pdf")
+            .within(Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(email.id, "k3u7m0c2x9");
+    }
+    #[tokio::test]
+    async fn reports_what_was_received() {
+        let error = client()
+            .expect()
+            .from("sender@example.com")
+            .has_attachment("receipt.pdf")
+            .within(Duration::ZERO)
+            .await
+            .unwrap_err();
+        let ExpectationError::Timeout { received, .. } = &error else {
+            panic!("{error}");
+        };
+        assert_eq!(received[0].reason, r#"has attachments ["invoice.pdf"]"#);
+        assert_eq!(
+            error.to_string(),
+            "expected a message from sender@example.com and with an attachment named \
+             \"receipt.pdf\" within 0ns, received 1:\n  \
+             - \"Your code is 123456\" from sender@example.com (k3u7m0c2x9): \
+             has attachments [\"invoice.pdf\"]"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Group messages into conversations.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{client::Client, types::Email};
+
+/// Messages from one sender, each received within a time window of the previous one,
+/// with the same subject, ignoring `Re:` and `Fwd:` prefixes, unless grouped by
+/// [`Grouping::Sender`].
+///
+/// The API doesn't expose `Message-ID` or `In-Reply-To` headers, so threads can't follow
+/// replies that change the subject.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Thread {
+    /// The first message's subject without reply and forward prefixes
+    pub subject: String,
+    /// Oldest first, never empty
+    emails: Vec<Email>,
+}
+
+/// What messages from the same sender must share to be in the same [`Thread`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Grouping {
+    /// The subject, ignoring reply and forward prefixes
+    #[default]
+    Subject,
+    /// Nothing but the time window, for sequences like a welcome, a verification and a
+    /// reminder, whose subjects differ
+    Sender,
+}
+
+impl Thread {
+    /// Group `emails` by sender and subject, see [`Thread::group_by`].
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use tmapi::{Client, Thread};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_threads.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let emails = client.get_all_emails().await.unwrap();
+    /// let threads = Thread::group(emails, Duration::from_secs(60 * 60));
+    /// assert_eq!(threads.len(), 3);
+    /// assert_eq!(threads[0].subject, "Welcome to Example");
+    /// assert_eq!(threads[0].len(), 2);
+    /// # });
+    /// ```
+    pub fn group(emails: impl IntoIterator<Item = Email>, max_gap: Duration) -> Vec<Self> {
+        Self::group_by(emails, Grouping::Subject, max_gap)
+    }
+
+    /// Group `emails` into threads, oldest thread first. A message joins the sender's
+    /// latest thread, with the same subject when grouping by [`Grouping::Subject`], if it
+    /// was received at most `max_gap` after that thread's last message.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use tmapi::{Client, Grouping, Thread};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_threads.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let emails = client.get_all_emails().await.unwrap();
+    /// let onboarding = Thread::group_by(emails, Grouping::Sender, Duration::from_secs(10 * 60));
+    /// let subjects: Vec<_> = onboarding[0].emails().iter().map(|email| &email.subject).collect();
+    /// assert_eq!(subjects, ["Welcome to Example", "Verify your email"]);
+    /// # });
+    /// ```
+    pub fn group_by(
+        emails: impl IntoIterator<Item = Email>,
+        grouping: Grouping,
+        max_gap: Duration,
+    ) -> Vec<Self> {
+        let mut emails: Vec<Email> = emails.into_iter().collect();
+        emails.sort_by_key(|email| email.received_at);
+        let mut threads: Vec<Thread> = vec![];
+        for email in emails {
+            let subject = normalize_subject(&email.subject);
+            let sender = email.from_address.email().to_lowercase();
+            let thread = threads.iter_mut().rev().find(|thread| {
+                let last = thread.last();
+                last.from_address.email().to_lowercase() == sender
+                    && (grouping == Grouping::Sender
+                        || thread.subject.to_lowercase() == subject.to_lowercase())
+            });
+            match thread {
+                Some(thread) if email.received_at - thread.last().received_at <= max_gap => {
+                    thread.emails.push(email)
+                }
+                _ => threads.push(Thread {
+                    subject,
+                    emails: vec![email],
+                }),
+            }
+        }
+        threads
+    }
+
+    /// The messages, oldest first.
+    pub fn emails(&self) -> &[Email] {
+        &self.emails
+    }
+
+    /// The messages, oldest first.
+    pub fn into_emails(self) -> Vec<Email> {
+        self.emails
+    }
+
+    /// The first message.
+    pub fn first(&self) -> &Email {
+        &self.emails[0]
+    }
+
+    /// The latest message.
+    pub fn last(&self) -> &Email {
+        &self.emails[self.emails.len() - 1]
+    }
+
+    /// When the first message was received.
+    pub fn started_at(&self) -> OffsetDateTime {
+        self.first().received_at
+    }
+
+    /// How many messages the thread has.
+    pub fn len(&self) -> usize {
+        self.emails.len()
+    }
+
+    /// Always `false`, threads have at least one message.
+    pub fn is_empty(&self) -> bool {
+        self.emails.is_empty()
+    }
+}
+
+impl Client {
+    /// Every message in the inbox grouped with [`Thread::group_by`].
+    ///
+    /// ## Example
+    /// ```
+    /// use std::time::Duration;
+    /// use tmapi::{Client, Grouping};
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_threads.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let hour = Duration::from_secs(60 * 60);
+    /// let threads = client.get_threads(Grouping::Subject, hour).await.unwrap();
+    /// for thread in &threads {
+    ///     println!("{} ({} messages)", thread.subject, thread.len());
+    /// }
+    /// assert_eq!(threads.iter().map(|thread| thread.len()).sum::<usize>(), 4);
+    /// # });
+    /// ```
+    pub async fn get_threads(
+        &self,
+        grouping: Grouping,
+        max_gap: Duration,
+    ) -> Result<Vec<Thread>, crate::ErrorType> {
+        Ok(Thread::group_by(
+            self.get_all_emails().await?,
+            grouping,
+            max_gap,
+        ))
+    }
+}
+
+/// `subject` without `Re:`, `Fw:` or `Fwd:` prefixes (also `Re[2]:`), with whitespace collapsed.
+fn normalize_subject(subject: &str) -> String {
+    let mut rest = subject.trim();
+    while let Some((prefix, after)) = rest.split_once(':') {
+        let prefix = prefix.trim_end();
+        let prefix = match prefix.find('[') {
+            Some(open)
+                if prefix.ends_with(']')
+                    && prefix[open + 1..prefix.len() - 1]
+                        .chars()
+                        .all(|c| c.is_ascii_digit()) =>
+            {
+                &prefix[..open]
+            }
+            _ => prefix,
+        };
+        if !["re", "fw", "fwd"].contains(&prefix.to_ascii_lowercase().as_str()) {
+            break;
+        }
+        rest = after.trim_start();
+    }
+    rest.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::sample_email;
+
+    fn email(id: &str, from: &str, subject: &str, received_at: i64) -> Email {
+        Email {
+            id: id.into(),
+            from_address: from.parse().unwrap(),
+            subject: subject.into(),
+            received_at: OffsetDateTime::from_unix_timestamp(received_at).unwrap(),
+            ..sample_email()
+        }
+    }
+
+    fn ids(threads: &[Thread]) -> Vec<Vec<&str>> {
+        threads
+            .iter()
+            .map(|thread| {
+                thread
+                    .emails()
+                    .iter()
+                    .map(|email| email.id.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn subjects() {
+        assert_eq!(normalize_subject("Re: FWD:  Your   order"), "Your order");
+        assert_eq!(normalize_subject("RE[2]: Welcome"), "Welcome");
+        assert_eq!(normalize_subject("Reminder: verify"), "Reminder: verify");
+    }
+    #[test]
+    fn grouping() {
+        let hour = Duration::from_secs(60 * 60);
+        let threads = Thread::group(
+            [
+                email("4", "a@barid.site", "Re: Welcome", 1_752_003_000),
+                email("1", "a@barid.site", "Welcome", 1_752_000_000),
+                email("2", "b@barid.site", "Welcome", 1_752_000_100),
+                email("3", "A@barid.site", "re: welcome", 1_752_001_000),
+                email("5", "a@barid.site", "Welcome", 1_752_010_000),
+            ],
+            hour,
+        );
+        assert_eq!(ids(&threads), [vec!["1", "3", "4"], vec!["2"], vec!["5"]]);
+        assert_eq!(threads[0].subject, "Welcome");
+        assert_eq!(threads[0].last().id, "4");
+    }
+    #[test]
+    fn grouping_by_sender() {
+        let emails = [
+            email("1", "a@barid.site", "Welcome!", 1_752_000_000),
+            email("2", "a@barid.site", "Verify your address", 1_752_000_060),
+            email("3", "a@barid.site", "Reminder: verify", 1_752_003_000),
+            email("4", "b@barid.site", "Welcome!", 1_752_000_100),
+        ];
+        let minutes = Duration::from_secs(10 * 60);
+        let threads = Thread::group_by(emails.clone(), Grouping::Sender, minutes);
+        assert_eq!(ids(&threads), [vec!["1", "2"], vec!["4"], vec!["3"]]);
+        assert_eq!(threads[0].subject, "Welcome!");
+        let threads = Thread::group_by(emails, Grouping::Sender, Duration::from_secs(60 * 60));
+        assert_eq!(ids(&threads), [vec!["1", "2", "3"], vec!["4"]]);
+    }
+}
@@ -0,0 +1,194 @@
+//! The HTTP layer every [`Client`] call goes through.
+//!
+//! [`ReqwestTransport`] is used by default. Implement [`Transport`] to add middleware,
+//! replay recorded responses in tests, or use another HTTP library, then pass it to
+//! [`Client::with_transport`].
+//!
+//! [`Client`]: crate::Client
+//! [`Client::with_transport`]: crate::Client::with_transport
+use std::fmt::{self, Debug};
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// The HTTP method of a [`HttpRequest`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A request to the API.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// A request without headers or body.
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::Get, url)
+    }
+    pub fn delete(url: impl Into<String>) -> Self {
+        Self::new(Method::Delete, url)
+    }
+}
+
+/// A response from the API.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Whether the status code is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+    /// The first value of a header, the name is case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    /// Decode the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// Sends [`HttpRequest`]s for a [`Client`].
+///
+/// Non-2xx responses are not errors, the API describes its errors in the body.
+/// Return an error only when no response was received.
+///
+/// ## Example
+/// ```
+/// use futures_util::future::BoxFuture;
+/// use tmapi::{Client, HttpRequest, HttpResponse, Transport};
+///
+/// #[derive(Debug)]
+/// struct Offline;
+///
+/// impl Transport for Offline {
+///     fn send(
+///         &self,
+///         _request: HttpRequest,
+///     ) -> BoxFuture<'_, Result<HttpResponse, Box<dyn std::error::Error + Send + Sync>>> {
+///         Box::pin(async { Err("offline".into()) })
+///     }
+/// }
+///
+/// let client = Client::new("y@iusearch.lol").unwrap().with_transport(Offline);
+/// ```
+///
+/// [`Client`]: crate::Client
+pub trait Transport: Debug + Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, crate::ErrorType>>;
+}
+
+/// The default [`Transport`], backed by a [reqwest] client.
+///
+/// Build it from your own `reqwest::Client` to configure proxies, timeouts or TLS.
+///
+/// [reqwest]: <https://docs.rs/reqwest>
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, crate::ErrorType>> {
+        Box::pin(async move {
+            let method = reqwest::Method::from_bytes(request.method.as_str().as_bytes())?;
+            let mut builder = self.client.request(method, request.url);
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+            if !request.body.is_empty() {
+                builder = builder.body(request.body);
+            }
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                })
+                .collect();
+            let body = response.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_helpers() {
+        let response = HttpResponse {
+            status: 404,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: br#"{"success":false}"#.to_vec(),
+        };
+        assert!(!response.is_success());
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        let value: serde_json::Value = response.json().unwrap();
+        assert_eq!(value["success"], false);
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use email_address::EmailAddress as RawAddress;
+use serde::{Deserialize, Serialize};
+
+pub use email_address::Error as AddressError;
+
+/// An email address, optionally carrying a display name (`Name <local@domain>`).
+///
+/// Two addresses are equal when their `local@domain` parts match case-insensitively,
+/// the display name is ignored. Invalid addresses are only equal to the exact same text.
+///
+/// Addresses received from the API are decoded leniently: senders that aren't valid
+/// addresses, like bounces from `<>`, are kept as they were sent, see [`EmailAddress::is_valid`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct EmailAddress(Inner);
+
+#[derive(Debug, Clone)]
+enum Inner {
+    Parsed(RawAddress),
+    /// Kept as received, the parts are split on a best-effort basis
+    Unparsed(String),
+}
+
+impl EmailAddress {
+    /// Parse an address, with or without a display name.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::EmailAddress;
+    ///
+    /// let address = EmailAddress::parse("Barid <y@iusearch.lol>").unwrap();
+    /// assert_eq!(address.local_part(), "y");
+    /// assert_eq!(address.domain(), "iusearch.lol");
+    /// assert_eq!(address.display_name(), Some("Barid"));
+    /// ```
+    pub fn parse<S>(address: S) -> Result<Self, AddressError>
+    where
+        S: AsRef<str>,
+    {
+        RawAddress::from_str(address.as_ref()).map(|address| Self(Inner::Parsed(address)))
+    }
+
+    /// Parse a bare `local@domain` address, rejecting display names.
+    pub(crate) fn parse_bare(address: &str) -> Result<Self, AddressError> {
+        let options = email_address::Options::default().without_display_text();
+        RawAddress::parse_with_options(address, options).map(|address| Self(Inner::Parsed(address)))
+    }
+
+    /// Whether the address is valid. Invalid addresses come from the API or `From<String>`, their parts
+    /// are guessed: the text between `<` and `>` if any, split at the last `@`.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::EmailAddress;
+    ///
+    /// let address = EmailAddress::from("MAILER-DAEMON".to_owned());
+    /// assert!(!address.is_valid());
+    /// assert_eq!(address.as_str(), "MAILER-DAEMON");
+    /// assert_eq!(address.domain(), "");
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        matches!(self.0, Inner::Parsed(_))
+    }
+
+    /// The part before the `@`.
+    pub fn local_part(&self) -> &str {
+        match &self.0 {
+            Inner::Parsed(address) => address.local_part(),
+            Inner::Unparsed(_) => self.split().0,
+        }
+    }
+
+    /// The part after the `@`.
+    pub fn domain(&self) -> &str {
+        match &self.0 {
+            Inner::Parsed(address) => address.domain(),
+            Inner::Unparsed(_) => self.split().1,
+        }
+    }
+
+    /// The display name, if the address had one.
+    pub fn display_name(&self) -> Option<&str> {
+        let name = match &self.0 {
+            Inner::Parsed(address) => address.display_part(),
+            Inner::Unparsed(raw) => raw.split_once('<').map_or("", |(name, _)| name.trim()),
+        };
+        Some(name).filter(|name| !name.is_empty())
+    }
+
+    /// The `local@domain` part of the address, without the display name.
+    pub fn email(&self) -> String {
+        match &self.0 {
+            Inner::Parsed(address) => address.email(),
+            Inner::Unparsed(_) => self.bare().to_owned(),
+        }
+    }
+
+    /// The address exactly as it was parsed.
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Inner::Parsed(address) => address.as_str(),
+            Inner::Unparsed(raw) => raw,
+        }
+    }
+
+    /// The text between `<` and `>`, or all of it.
+    fn bare(&self) -> &str {
+        let raw = self.as_str().trim();
+        raw.split_once('<')
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map_or(raw, |(bare, _)| bare.trim())
+    }
+
+    fn split(&self) -> (&str, &str) {
+        let bare = self.bare();
+        bare.rsplit_once('@').unwrap_or((bare, ""))
+    }
+}
+
+impl PartialEq for EmailAddress {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Inner::Parsed(_), Inner::Parsed(_)) => {
+                self.local_part().eq_ignore_ascii_case(other.local_part())
+                    && self.domain().eq_ignore_ascii_case(other.domain())
+            }
+            (Inner::Unparsed(raw), Inner::Unparsed(other)) => raw == other,
+            _ => false,
+        }
+    }
+}
+impl Eq for EmailAddress {}
+
+impl Hash for EmailAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.0 {
+            Inner::Parsed(_) => {
+                self.local_part().to_ascii_lowercase().hash(state);
+                self.domain().to_ascii_lowercase().hash(state);
+            }
+            Inner::Unparsed(raw) => raw.hash(state),
+        }
+    }
+}
+
+impl Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EmailAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Never fails, invalid addresses are kept as they are.
+impl From<String> for EmailAddress {
+    fn from(value: String) -> Self {
+        Self::parse(&value).unwrap_or(Self(Inner::Unparsed(value)))
+    }
+}
+
+impl From<EmailAddress> for String {
+    fn from(value: EmailAddress) -> Self {
+        match value.0 {
+            Inner::Parsed(address) => address.into(),
+            Inner::Unparsed(raw) => raw,
+        }
+    }
+}
+
+impl AsRef<str> for EmailAddress {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let address = EmailAddress::parse("y@iusearch.lol").unwrap();
+        assert_eq!(address.local_part(), "y");
+        assert_eq!(address.domain(), "iusearch.lol");
+        assert_eq!(address.display_name(), None);
+        assert_eq!(address.email(), "y@iusearch.lol");
+    }
+    #[test]
+    fn case_insensitive_equality() {
+        let lower = EmailAddress::parse("noreply@example.com").unwrap();
+        let upper = EmailAddress::parse("Example <NoReply@EXAMPLE.com>").unwrap();
+        assert_eq!(lower, upper);
+    }
+    #[test]
+    fn bare_rejects_display_name() {
+        assert!(EmailAddress::parse_bare("Y <y@iusearch.lol>").is_err());
+        assert!(EmailAddress::parse_bare("y@iusearch.lol").is_ok());
+    }
+    #[test]
+    fn invalid_address() {
+        assert_eq!(
+            EmailAddress::parse("y"),
+            Err(AddressError::MissingSeparator)
+        );
+    }
+    #[test]
+    fn lenient_decoding() {
+        let address: EmailAddress = serde_json::from_str(r#""Mail Delivery <>""#).unwrap();
+        assert!(!address.is_valid());
+        assert_eq!(address.as_str(), "Mail Delivery <>");
+        assert_eq!(address.display_name(), Some("Mail Delivery"));
+        assert_eq!(address.email(), "");
+        let address: EmailAddress = serde_json::from_str(r#""bounce@""#).unwrap();
+        assert_eq!((address.local_part(), address.domain()), ("bounce", ""));
+        assert_eq!(serde_json::to_string(&address).unwrap(), r#""bounce@""#);
+        let address: EmailAddress = serde_json::from_str(r#""y@iusearch.lol""#).unwrap();
+        assert!(address.is_valid());
+    }
+    #[test]
+    fn unparsed_equality() {
+        let empty = EmailAddress::from(String::new());
+        let bounce = EmailAddress::from("<>".to_owned());
+        assert_ne!(empty, bounce);
+        assert_eq!(bounce, EmailAddress::from("<>".to_owned()));
+        assert_ne!(
+            EmailAddress::from("Y@".to_owned()),
+            EmailAddress::from("y@".to_owned())
+        );
+        let addresses: std::collections::HashSet<_> = [empty, bounce].into_iter().collect();
+        assert_eq!(addresses.len(), 2);
+    }
+}
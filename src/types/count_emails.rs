@@ -1,74 +1,67 @@
-use crate::client::API_URL;
-use crate::types::Error;
 use serde::Deserialize;
 
-use crate::{client::Client, types::errors::EmailsError};
-#[derive(Debug, Deserialize, Clone)]
-struct CountResponse {
-    success: bool,
-    result: Option<CountResult>,
-    error: Option<Error>,
-    note: Option<SupportedDomainsNote>,
-}
+use crate::{
+    client::Client,
+    transport::Method,
+    types::{address::EmailAddress, endpoint::Endpoint},
+};
+
 #[derive(Debug, Deserialize, Clone, Copy)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 struct CountResult {
     count: u32,
 }
-#[derive(Debug, Deserialize, Clone)]
-struct SupportedDomainsNote {
-    #[serde(rename = "supportedDomains")]
-    supported_domains: Vec<String>,
+
+struct EmailCount<'a> {
+    address: &'a EmailAddress,
+}
+
+impl Endpoint for EmailCount<'_> {
+    const NAME: &'static str = "email_count";
+    const METHOD: Method = Method::Get;
+    type Result = CountResult;
+
+    fn path(&self) -> String {
+        format!("/emails/count/{}", self.address)
+    }
 }
 
 impl Client {
     /// Get message count for an email.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/count_emails.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let emails = client.email_count().await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn email_count(&self) -> Result<u32, crate::ErrorType> {
-        let url = format!("{API_URL}/emails/count/{}", self.email);
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<CountResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap().count)
-        } else {
-            Err(Box::new(EmailsError::from_email_count(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_email_count(response: CountResponse) -> Self {
-        let error = response.error.unwrap();
-
-        match response.note {
-            Some(note) => Self::DomainError {
-                name: error.name,
-                message: error.description,
-                supported_domains: note.supported_domains,
-            },
-            None => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
-        }
+        self.call(EmailCount {
+            address: &self.email,
+        })
+        .await
+        .map(|result| result.count)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::{ApiResponse, SupportedDomainsNote};
+    use crate::types::errors::EmailsError;
 
     #[test]
     fn domain_error_conversion() {
-        let response = CountResponse {
-            success: false,
+        let response = ApiResponse::<CountResult> {
+            success: Some(false),
             error: Some(Error {
                 name: "DomainError".into(),
                 description: "Invalid domain".into(),
@@ -78,20 +71,21 @@ mod tests {
             }),
             result: None,
         };
-        let error = EmailsError::from_email_count(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::DomainError {
                 name: "DomainError".into(),
                 message: "Invalid domain".into(),
-                supported_domains: vec![]
+                supported_domains: vec![],
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = CountResponse {
-            success: false,
+        let response = ApiResponse::<CountResult> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
@@ -99,12 +93,34 @@ mod tests {
             note: None,
             result: None,
         };
-        let error = EmailsError::from_email_count(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<CountResult> = fixture!("count_emails/success.json");
+        assert_eq!(response.success, Some(true));
+        assert_eq!(response.result.unwrap().count, 3);
+    }
+    #[test]
+    fn domain_error_fixture() {
+        let response: ApiResponse<CountResult> = fixture!("count_emails/domain_error.json");
+        assert_eq!(response.success, Some(false));
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::DomainError {
+                name: "ValidationError".into(),
+                message: "Unsupported domain".into(),
+                supported_domains: vec!["barid.site".into(), "iusearch.lol".into()],
+                status: None,
             }
         )
     }
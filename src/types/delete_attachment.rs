@@ -1,78 +1,101 @@
-use serde::Deserialize;
+use serde::de::IgnoredAny;
 
 use crate::{
-    client::{API_URL, Client},
-    types::{Error, errors::EmailsError},
+    client::Client,
+    transport::Method,
+    types::{
+        endpoint::{Endpoint, require_id},
+        errors::EmailsError,
+    },
 };
 
-#[derive(Debug, Deserialize, Clone)]
-struct DeleteAttachmentResponse {
-    success: bool,
-    error: Option<Error>,
+struct DeleteAttachment {
+    id: String,
+}
+
+impl Endpoint for DeleteAttachment {
+    const NAME: &'static str = "delete_attachment";
+    const METHOD: Method = Method::Delete;
+    type Result = IgnoredAny;
+
+    fn path(&self) -> String {
+        format!("/attachments/{}", self.id)
+    }
+    fn validate(&self) -> Result<(), EmailsError> {
+        require_id(&self.id, "Attachment not found")
+    }
 }
 
 impl Client {
     /// Delete a specific attachment by its ID.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_attachment.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
-    /// let deleted_count = client.delete_attachment("att_usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
-    /// # };
+    /// # let client = client.with_transport(vcr);
+    /// let deleted_count = client.delete_attachment("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
+    /// # });
     /// ```
     pub async fn delete_attachment<S>(&self, attachment_id: S) -> Result<(), crate::ErrorType>
     where
         S: Into<String>,
     {
-        let attachment_id: String = attachment_id.into();
-        if attachment_id.is_empty() {
-            return Err(Box::new(EmailsError::NotFoundError {
-                name: "NotFound".to_owned(),
-                message: "Attachment not found".to_owned(),
-            }));
-        }
-        let url = format!("{API_URL}/attachments/{attachment_id}");
-        let response = self.client.delete(url).send().await?;
-        let response = response.json::<DeleteAttachmentResponse>().await?;
-        if response.success {
-            Ok(())
-        } else {
-            Err(Box::new(EmailsError::from_delete_attachment(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_delete_attachment(response: DeleteAttachmentResponse) -> Self {
-        let error = response.error.unwrap();
-
-        Self::ValidationError {
-            name: error.name,
-            message: error.description,
-        }
+        self.call(DeleteAttachment {
+            id: attachment_id.into(),
+        })
+        .await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
 
     #[test]
     fn error_conversion() {
-        let response = DeleteAttachmentResponse {
-            success: false,
+        let response = ApiResponse::<IgnoredAny> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
+            note: None,
+            result: None,
         };
-        let error = EmailsError::from_delete_attachment(response);
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::ValidationError {
+                name: "ValidationError".into(),
+                message: "Invalid input".into(),
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<IgnoredAny> = fixture!("delete_attachment/success.json");
+        assert_eq!(response.success, Some(true));
+        assert!(response.error.is_none());
+    }
+    #[test]
+    fn error_fixture() {
+        let response: ApiResponse<IgnoredAny> = fixture!("delete_attachment/error.json");
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
-                message: "Invalid input".into()
+                message: "Invalid attachment ID".into(),
+                status: None,
             }
         )
     }
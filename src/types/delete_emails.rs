@@ -1,74 +1,100 @@
 use serde::Deserialize;
 
 use crate::{
-    client::{API_URL, Client},
-    types::{Error, errors::EmailsError},
+    client::Client,
+    transport::Method,
+    types::{address::EmailAddress, endpoint::Endpoint},
 };
 
 #[derive(Debug, Deserialize, Clone)]
-struct DeleteResponse {
-    success: bool,
-    error: Option<Error>,
-    result: Option<DeleteResult>,
-}
-#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 struct DeleteResult {
     deleted_count: u32,
 }
+
+struct DeleteAllEmails<'a> {
+    address: &'a EmailAddress,
+}
+
+impl Endpoint for DeleteAllEmails<'_> {
+    const NAME: &'static str = "delete_all_emails";
+    const METHOD: Method = Method::Delete;
+    type Result = DeleteResult;
+
+    fn path(&self) -> String {
+        format!("/emails/{}", self.address)
+    }
+}
+
 impl Client {
     /// Delete all emails in your inbox.
     /// Returns how many emails where deleted in the operation.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_emails.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let deleted_count = client.delete_all_emails().await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn delete_all_emails(&self) -> Result<u32, crate::ErrorType> {
-        let url = format!("{API_URL}/emails/{}", self.email);
-        let response = self.client.delete(url).send().await?;
-        let response = response.json::<DeleteResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap().deleted_count)
-        } else {
-            Err(Box::new(EmailsError::from_delete_emails(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_delete_emails(response: DeleteResponse) -> Self {
-        let error = response.error.unwrap();
-
-        Self::ValidationError {
-            name: error.name,
-            message: error.description,
-        }
+        self.call(DeleteAllEmails {
+            address: &self.email,
+        })
+        .await
+        .map(|result| result.deleted_count)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
+    use crate::types::errors::EmailsError;
 
     #[test]
     fn error_conversion() {
-        let response = DeleteResponse {
-            success: false,
+        let response = ApiResponse::<DeleteResult> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
             result: None,
+            note: None,
         };
-        let error = EmailsError::from_delete_emails(response);
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::ValidationError {
+                name: "ValidationError".into(),
+                message: "Invalid input".into(),
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<DeleteResult> = fixture!("delete_emails/success.json");
+        assert_eq!(response.success, Some(true));
+        assert_eq!(response.result.unwrap().deleted_count, 2);
+    }
+    #[test]
+    fn error_fixture() {
+        let response: ApiResponse<DeleteResult> = fixture!("delete_emails/error.json");
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
-                message: "Invalid input".into()
+                message: "Invalid email address".into(),
+                status: None,
             }
         )
     }
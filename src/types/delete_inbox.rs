@@ -1,71 +1,93 @@
-use serde::Deserialize;
+use serde::de::IgnoredAny;
 
-use crate::{
-    client::{API_URL, Client},
-    types::{Error, errors::EmailsError},
-};
+use crate::{client::Client, transport::Method, types::endpoint::Endpoint};
 
-#[derive(Debug, Deserialize, Clone)]
-struct DeleteResponse {
-    success: bool,
-    error: Option<Error>,
+struct DeleteInbox {
+    id: String,
 }
+
+impl Endpoint for DeleteInbox {
+    const NAME: &'static str = "delete_inbox";
+    const METHOD: Method = Method::Delete;
+    type Result = IgnoredAny;
+
+    fn path(&self) -> String {
+        format!("/inbox/{}", self.id)
+    }
+}
+
 impl Client {
     /// Delete a specific email from your inbox.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/delete_inbox.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
-    /// client.delete_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
-    /// # };
+    /// # let client = client.with_transport(vcr);
+    /// client.delete_inbox("k3u7m0c2x9").await.unwrap();
+    /// # });
     /// ```
     pub async fn delete_inbox<S>(&self, email_id: S) -> Result<(), crate::ErrorType>
     where
         S: Into<String>,
     {
-        let url = format!("{API_URL}/inbox/{}", email_id.into());
-        let response = self.client.delete(url).send().await?;
-        let response = response.json::<DeleteResponse>().await?;
-        if response.success {
-            Ok(())
-        } else {
-            Err(Box::new(EmailsError::from_delete_inbox(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_delete_inbox(response: DeleteResponse) -> Self {
-        let error = response.error.unwrap();
-
-        Self::ValidationError {
-            name: error.name,
-            message: error.description,
-        }
+        self.call(DeleteInbox {
+            id: email_id.into(),
+        })
+        .await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
+    use crate::types::errors::EmailsError;
 
     #[test]
     fn error_conversion() {
-        let response = DeleteResponse {
-            success: false,
+        let response = ApiResponse::<IgnoredAny> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
+            note: None,
+            result: None,
         };
-        let error = EmailsError::from_delete_inbox(response);
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::ValidationError {
+                name: "ValidationError".into(),
+                message: "Invalid input".into(),
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<IgnoredAny> = fixture!("delete_inbox/success.json");
+        assert_eq!(response.success, Some(true));
+        assert!(response.error.is_none());
+    }
+    #[test]
+    fn error_fixture() {
+        let response: ApiResponse<IgnoredAny> = fixture!("delete_inbox/error.json");
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
-                message: "Invalid input".into()
+                message: "Invalid email ID".into(),
+                status: None,
             }
         )
     }
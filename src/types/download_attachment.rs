@@ -1,24 +1,47 @@
-use crate::client::API_URL;
-use crate::types::Error;
-use serde::Deserialize;
+use serde::de::IgnoredAny;
 
-use crate::{client::Client, types::errors::EmailsError};
-#[derive(Debug, Deserialize, Clone)]
-struct DownloadAttachmentResponse {
-    error: Option<Error>,
+use crate::{
+    client::Client,
+    transport::Method,
+    types::{
+        endpoint::{Endpoint, error_from, require_id},
+        errors::EmailsError,
+    },
+};
+
+/// Answers with the raw content on success, and with the usual envelope on failure.
+struct DownloadAttachment {
+    id: String,
+}
+
+impl Endpoint for DownloadAttachment {
+    const NAME: &'static str = "download_attachment";
+    const METHOD: Method = Method::Get;
+    type Result = IgnoredAny;
+
+    fn path(&self) -> String {
+        format!("/attachments/{}", self.id)
+    }
+    fn validate(&self) -> Result<(), EmailsError> {
+        require_id(&self.id, "Attachment not found")
+    }
 }
 
 impl Client {
     /// Download an attachment by its ID.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/download_attachment.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let emails = client.download_attachment("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn download_attachment<S>(
         &self,
@@ -27,73 +50,75 @@ impl Client {
     where
         S: Into<String>,
     {
-        let attachment_id: String = attachment_id.into();
-        if attachment_id.is_empty() {
-            return Err(Box::new(EmailsError::NotFoundError {
-                name: "NotFound".to_owned(),
-                message: "Attachment not found".to_owned(),
-            }));
-        }
-        let url = format!("{API_URL}/attachments/{attachment_id}");
-        let response = self.client.get(url).send().await?;
-        if response.status().is_success() {
-            return Ok(response.bytes().await.unwrap().to_vec());
-        }
-        let response = response.json::<DownloadAttachmentResponse>().await?;
-        Err(Box::new(EmailsError::from_download_attachment(response)))
-    }
-}
-impl EmailsError {
-    fn from_download_attachment(response: DownloadAttachmentResponse) -> Self {
-        let error = response.error.unwrap();
-        match error.name.as_str() {
-            "NotFound" => Self::NotFoundError {
-                name: error.name,
-                message: error.description,
-            },
-            _ => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
-        }
+        let endpoint = DownloadAttachment {
+            id: attachment_id.into(),
+        };
+        self.instrument(DownloadAttachment::NAME, async {
+            let response = self.request(&endpoint).await?;
+            if response.is_success() {
+                let bytes = response.body;
+                crate::telemetry::record_download(bytes.len());
+                return Ok(bytes);
+            }
+            Err(error_from(&response).into())
+        })
+        .await
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
 
     #[test]
     fn notfound_error_conversion() {
-        let response = DownloadAttachmentResponse {
+        let response = ApiResponse::<IgnoredAny> {
+            success: Some(false),
             error: Some(Error {
                 name: "NotFound".into(),
                 description: "Attachment not found".into(),
             }),
+            note: None,
+            result: None,
         };
-        let error = EmailsError::from_download_attachment(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::NotFoundError {
                 name: "NotFound".into(),
                 message: "Attachment not found".into(),
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = DownloadAttachmentResponse {
+        let response = ApiResponse::<IgnoredAny> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
+            note: None,
+            result: None,
         };
-        let error = EmailsError::from_download_attachment(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
             }
         )
     }
+    #[test]
+    fn not_found_fixture() {
+        let response: ApiResponse<IgnoredAny> = fixture!("download_attachment/not_found.json");
+        let error = response.into_error();
+        assert!(error.is_not_found());
+        assert_eq!(error.message(), "Attachment not found");
+    }
 }
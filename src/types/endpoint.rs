@@ -0,0 +1,189 @@
+//! The response envelope shared by every endpoint, and how endpoints are described.
+use serde::{
+    Deserialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+
+use crate::{
+    client::{API_URL, Client},
+    transport::{HttpRequest, HttpResponse, Method},
+    types::{Error, errors::EmailsError},
+};
+
+/// `{success, result, error, note}`, as returned by every endpoint.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub(super) struct ApiResponse<T> {
+    /// Missing from some endpoints, `error` tells failures apart then
+    pub(super) success: Option<bool>,
+    pub(super) result: Option<T>,
+    pub(super) error: Option<Error>,
+    pub(super) note: Option<SupportedDomainsNote>,
+}
+
+/// Sent along domain errors.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub(super) struct SupportedDomainsNote {
+    #[serde(rename = "supportedDomains")]
+    pub(super) supported_domains: Vec<String>,
+}
+
+impl<T: DeserializeOwned> ApiResponse<T> {
+    /// The result of a successful response, or the error it describes.
+    ///
+    /// A successful response without a result is only accepted when `T` can be decoded from
+    /// `null`, like `()` or [`serde::de::IgnoredAny`].
+    pub(super) fn into_result(self) -> Result<T, crate::ErrorType> {
+        if self.is_failure() {
+            return Err(self.into_error().into());
+        }
+        match self.result {
+            Some(result) => Ok(result),
+            None => T::deserialize(serde_json::Value::Null)
+                .map_err(|_| "the response has no result".into()),
+        }
+    }
+}
+
+impl<T> ApiResponse<T> {
+    fn is_failure(&self) -> bool {
+        self.error.is_some() || self.success == Some(false)
+    }
+
+    /// The error described by a failed response.
+    pub(super) fn into_error(self) -> EmailsError {
+        match self.error {
+            Some(error) => EmailsError::classify(error, self.note.map(|n| n.supported_domains)),
+            None => EmailsError::UnknownError {
+                name: "UnknownError".into(),
+                message: "the request failed without an error".into(),
+                status: None,
+            },
+        }
+    }
+}
+
+/// The result of `response`, or the error it describes.
+pub(super) fn decode<T: DeserializeOwned>(response: HttpResponse) -> Result<T, crate::ErrorType> {
+    if !response.is_success() {
+        return Err(error_from(&response).into());
+    }
+    let envelope = response.json::<ApiResponse<T>>()?;
+    if envelope.is_failure() {
+        return Err(envelope.into_error().with_status(response.status).into());
+    }
+    envelope.into_result()
+}
+
+/// The error described by a failed response: the envelope's error when there is one,
+/// otherwise the one matching the HTTP status, see [`EmailsError::from_status`].
+pub(super) fn error_from(response: &HttpResponse) -> EmailsError {
+    match response.json::<ApiResponse<IgnoredAny>>() {
+        Ok(envelope) if envelope.error.is_some() => {
+            envelope.into_error().with_status(response.status)
+        }
+        _ => EmailsError::from_status(response.status, &String::from_utf8_lossy(&response.body)),
+    }
+}
+
+/// An API endpoint: how to request it and what a successful response contains.
+pub(super) trait Endpoint {
+    /// Name used for spans and metrics
+    const NAME: &'static str;
+    const METHOD: Method;
+    /// The `result` of a successful response
+    type Result: DeserializeOwned;
+
+    /// The path after [`API_URL`], with the query string.
+    fn path(&self) -> String;
+
+    /// Reject requests that can't succeed before sending them.
+    fn validate(&self) -> Result<(), EmailsError> {
+        Ok(())
+    }
+}
+
+/// A [`EmailsError::NotFoundError`] for empty IDs, which would otherwise hit another endpoint.
+pub(super) fn require_id(id: &str, message: &str) -> Result<(), EmailsError> {
+    if id.is_empty() {
+        return Err(EmailsError::NotFoundError {
+            name: "NotFound".to_owned(),
+            message: message.to_owned(),
+            status: None,
+        });
+    }
+    Ok(())
+}
+
+impl Client {
+    /// Send `endpoint`'s request without decoding the response.
+    pub(super) async fn request<E: Endpoint>(
+        &self,
+        endpoint: &E,
+    ) -> Result<HttpResponse, crate::ErrorType> {
+        endpoint.validate()?;
+        let url = format!("{API_URL}{}", endpoint.path());
+        self.send(HttpRequest::new(E::METHOD, url)).await
+    }
+
+    /// Call `endpoint` and decode its result.
+    pub(super) async fn call<E: Endpoint>(
+        &self,
+        endpoint: E,
+    ) -> Result<E::Result, crate::ErrorType> {
+        self.instrument(E::NAME, async { decode(self.request(&endpoint).await?) })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::IgnoredAny;
+
+    use super::*;
+
+    #[test]
+    fn missing_result() {
+        let response: ApiResponse<IgnoredAny> =
+            serde_json::from_str(r#"{"success":true}"#).unwrap();
+        assert!(response.into_result().is_ok());
+        let response: ApiResponse<Vec<String>> =
+            serde_json::from_str(r#"{"success":true}"#).unwrap();
+        assert!(response.into_result().is_err());
+    }
+    #[test]
+    fn failure_without_error() {
+        let response: ApiResponse<Vec<String>> =
+            serde_json::from_str(r#"{"success":false,"result":[]}"#).unwrap();
+        let error = response.into_result().unwrap_err();
+        let error = error.downcast_ref::<EmailsError>().unwrap();
+        assert_eq!(error.status_code(), None);
+    }
+    #[test]
+    fn status_without_envelope() {
+        let response = HttpResponse {
+            status: 429,
+            headers: vec![],
+            body: b"Too Many Requests".to_vec(),
+        };
+        let error = decode::<Vec<String>>(response).unwrap_err();
+        let error = error.downcast_ref::<EmailsError>().unwrap();
+        assert!(matches!(error, EmailsError::RateLimitError { .. }));
+        assert_eq!(error.message(), "Too Many Requests");
+        assert_eq!(error.status_code(), Some(429));
+    }
+    #[test]
+    fn envelope_on_error_status() {
+        let response = HttpResponse {
+            status: 404,
+            headers: vec![],
+            body: include_bytes!("../../tests/fixtures/download_attachment/not_found.json")
+                .to_vec(),
+        };
+        let error = error_from(&response);
+        assert!(error.is_not_found());
+        assert_eq!(error.message(), "Attachment not found");
+        assert_eq!(error.status_code(), Some(404));
+    }
+}
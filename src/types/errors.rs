@@ -1,13 +1,21 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error as ErrorTrait;
 use thiserror::Error;
 
+use super::Error as ApiError;
+
 pub(crate) type ErrorType = Box<dyn ErrorTrait + Send + Sync>;
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Error, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EmailsError {
     /// This error occurs when the given input is in bad format, for both emails and inbox IDs.
     #[error("Invalid input")]
-    ValidationError { name: String, message: String },
+    ValidationError {
+        name: String,
+        message: String,
+        #[serde(default)]
+        status: Option<u16>,
+    },
     /// This error occurs when using a domain that is not supported by the service.
     /// For supported domains see [`Client::get_domains`]
     ///
@@ -17,8 +25,238 @@ pub enum EmailsError {
         name: String,
         message: String,
         supported_domains: Vec<String>,
+        #[serde(default)]
+        status: Option<u16>,
     },
     /// This error occurs when requesting something that does not exist, like an invalid Inbox ID.
     #[error("Not found")]
-    NotFoundError { name: String, message: String },
+    NotFoundError {
+        name: String,
+        message: String,
+        #[serde(default)]
+        status: Option<u16>,
+    },
+    /// This error occurs when too many requests were made, try again later.
+    #[error("Rate limited")]
+    RateLimitError {
+        name: String,
+        message: String,
+        #[serde(default)]
+        status: Option<u16>,
+    },
+    /// This error occurs when the service failed to handle a valid request.
+    #[error("Internal server error")]
+    InternalError {
+        name: String,
+        message: String,
+        #[serde(default)]
+        status: Option<u16>,
+    },
+    /// An error name this version of the crate doesn't know about.
+    #[error("Unknown error: {name}")]
+    UnknownError {
+        name: String,
+        message: String,
+        #[serde(default)]
+        status: Option<u16>,
+    },
+}
+
+impl EmailsError {
+    /// Map an API error to its variant by name. A `note` listing the supported domains
+    /// always makes it a [`EmailsError::DomainError`].
+    pub(super) fn classify(error: ApiError, supported_domains: Option<Vec<String>>) -> Self {
+        let (name, message) = (error.name, error.description);
+        // Set by `with_status` once the response is known.
+        let status = None;
+        if let Some(supported_domains) = supported_domains {
+            return Self::DomainError {
+                name,
+                message,
+                supported_domains,
+                status,
+            };
+        }
+        let normalized = name.to_ascii_lowercase().replace(['_', '-', ' '], "");
+        match normalized.strip_suffix("error").unwrap_or(&normalized) {
+            "notfound" => Self::NotFoundError {
+                name,
+                message,
+                status,
+            },
+            "validation" | "badrequest" | "invalidinput" => Self::ValidationError {
+                name,
+                message,
+                status,
+            },
+            "domain" => Self::DomainError {
+                name,
+                message,
+                supported_domains: vec![],
+                status,
+            },
+            "ratelimit" | "ratelimited" | "ratelimitexceeded" | "toomanyrequests" => {
+                Self::RateLimitError {
+                    name,
+                    message,
+                    status,
+                }
+            }
+            "internal" | "internalserver" | "server" => Self::InternalError {
+                name,
+                message,
+                status,
+            },
+            _ => Self::UnknownError {
+                name,
+                message,
+                status,
+            },
+        }
+    }
+
+    /// Map a failed response that has no error envelope by its HTTP status, with the
+    /// status as the name and `body` as the message.
+    pub(super) fn from_status(code: u16, body: &str) -> Self {
+        let name = format!("HTTP {code}");
+        let message = body.trim().to_owned();
+        let status = Some(code);
+        match code {
+            400 | 422 => Self::ValidationError {
+                name,
+                message,
+                status,
+            },
+            404 => Self::NotFoundError {
+                name,
+                message,
+                status,
+            },
+            429 => Self::RateLimitError {
+                name,
+                message,
+                status,
+            },
+            500..=599 => Self::InternalError {
+                name,
+                message,
+                status,
+            },
+            _ => Self::UnknownError {
+                name,
+                message,
+                status,
+            },
+        }
+    }
+
+    /// The same error, read from a response with HTTP status `code`.
+    pub(super) fn with_status(mut self, code: u16) -> Self {
+        match &mut self {
+            Self::ValidationError { status, .. }
+            | Self::DomainError { status, .. }
+            | Self::NotFoundError { status, .. }
+            | Self::RateLimitError { status, .. }
+            | Self::InternalError { status, .. }
+            | Self::UnknownError { status, .. } => *status = Some(code),
+        }
+        self
+    }
+
+    /// The error name as sent by the API.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::ValidationError { name, .. }
+            | Self::DomainError { name, .. }
+            | Self::NotFoundError { name, .. }
+            | Self::RateLimitError { name, .. }
+            | Self::InternalError { name, .. }
+            | Self::UnknownError { name, .. } => name,
+        }
+    }
+
+    /// The error message as sent by the API.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::ValidationError { message, .. }
+            | Self::DomainError { message, .. }
+            | Self::NotFoundError { message, .. }
+            | Self::RateLimitError { message, .. }
+            | Self::InternalError { message, .. }
+            | Self::UnknownError { message, .. } => message,
+        }
+    }
+
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimitError { .. } | Self::InternalError { .. }
+        )
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::NotFoundError { .. })
+    }
+
+    /// The HTTP status of the response the error was read from, `None` for errors found
+    /// before sending the request.
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::ValidationError { status, .. }
+            | Self::DomainError { status, .. }
+            | Self::NotFoundError { status, .. }
+            | Self::RateLimitError { status, .. }
+            | Self::InternalError { status, .. }
+            | Self::UnknownError { status, .. } => *status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(name: &str) -> EmailsError {
+        let error = ApiError {
+            name: name.into(),
+            description: "message".into(),
+        };
+        EmailsError::classify(error, None)
+    }
+
+    #[test]
+    fn classification() {
+        assert!(classify("NotFound").is_not_found());
+        assert!(matches!(
+            classify("RateLimitExceeded"),
+            EmailsError::RateLimitError { .. }
+        ));
+        assert_eq!(classify("ValidationError").status_code(), None);
+        assert_eq!(
+            classify("NotFound").with_status(404).status_code(),
+            Some(404)
+        );
+        assert!(classify("TOO_MANY_REQUESTS").is_retryable());
+        assert!(classify("InternalServerError").is_retryable());
+        assert!(!classify("ValidationError").is_retryable());
+        assert_eq!(
+            classify("Unauthorized"),
+            EmailsError::UnknownError {
+                name: "Unauthorized".into(),
+                message: "message".into(),
+                status: None,
+            }
+        );
+    }
+    #[test]
+    fn note_makes_domain_error() {
+        let error = ApiError {
+            name: "ValidationError".into(),
+            description: "message".into(),
+        };
+        let error = EmailsError::classify(error, Some(vec!["barid.site".into()]));
+        assert_eq!(error.name(), "ValidationError");
+        assert!(matches!(error, EmailsError::DomainError { .. }));
+    }
 }
@@ -0,0 +1,62 @@
+//! Sample data from `tests/fixtures` shared by the tests of every module.
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+
+use super::{Email, endpoint::ApiResponse};
+use crate::transport::{HttpRequest, HttpResponse, Method, Transport};
+
+/// The message from `get_inbox/success.json`. Replace fields with struct update syntax:
+/// `Email { id: "a".into(), ..sample_email() }`.
+pub(crate) fn sample_email() -> Email {
+    let response: ApiResponse<Email> = fixture!("get_inbox/success.json");
+    response.into_result().unwrap()
+}
+
+/// A [`Transport`] answering with fixtures, remembering every request as `METHOD url`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FixtureTransport {
+    routes: Vec<(Method, &'static str, &'static str)>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl FixtureTransport {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+    /// Answer `method` requests whose URL contains `url` with the fixture at `path`.
+    /// Routes are tried in the order they were added.
+    pub(crate) fn route(mut self, method: Method, url: &'static str, path: &'static str) -> Self {
+        self.routes.push((method, url, path));
+        self
+    }
+    /// The requests sent so far, by every clone of this transport.
+    pub(crate) fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+    pub(crate) fn clear(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, crate::ErrorType>> {
+        let line = format!("{} {}", request.method, request.url);
+        self.requests.lock().unwrap().push(line.clone());
+        let route = self
+            .routes
+            .iter()
+            .find(|(method, url, _)| *method == request.method && request.url.contains(url));
+        Box::pin(async move {
+            let Some((_, _, path)) = route else {
+                return Err(format!("no fixture for {line}").into());
+            };
+            let path = format!("{}/tests/fixtures/{path}", env!("CARGO_MANIFEST_DIR"));
+            Ok(HttpResponse {
+                status: 200,
+                headers: vec![],
+                body: std::fs::read(path)?,
+            })
+        })
+    }
+}
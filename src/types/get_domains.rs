@@ -1,28 +1,48 @@
-use crate::client::API_URL;
-use serde::Deserialize;
+use crate::{client::Client, transport::Method, types::endpoint::Endpoint};
 
-use crate::client::Client;
-#[derive(Debug, Deserialize, Clone)]
-struct DomainsResponse {
-    result: Vec<String>,
+struct GetDomains;
+
+impl Endpoint for GetDomains {
+    const NAME: &'static str = "get_domains";
+    const METHOD: Method = Method::Get;
+    type Result = Vec<String>;
+
+    fn path(&self) -> String {
+        "/domains".into()
+    }
 }
 
 impl Client {
     /// Get the available domains for emails
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_domains.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let domains = client.get_domains().await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn get_domains(&self) -> Result<Vec<String>, crate::ErrorType> {
-        let url = format!("{API_URL}/domains");
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<DomainsResponse>().await?;
-        Ok(response.result)
+        self.call(GetDomains).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::endpoint::ApiResponse;
+
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<Vec<String>> = fixture!("get_domains/success.json");
+        assert_eq!(
+            response.into_result().unwrap(),
+            ["barid.site", "iusearch.lol"]
+        );
     }
 }
@@ -1,19 +1,26 @@
-use crate::types::Error;
-use crate::{client::API_URL, types::Attachment};
-use serde::Deserialize;
+use crate::{
+    client::Client,
+    transport::Method,
+    types::{Attachment, address::EmailAddress, endpoint::Endpoint, get_emails::PAGE_SIZE},
+};
 
-use crate::{client::Client, types::errors::EmailsError};
-#[derive(Debug, Deserialize, Clone)]
-struct GetEmailAttachmentsResponse {
-    success: bool,
-    result: Option<Vec<Attachment>>,
-    error: Option<Error>,
-    note: Option<SupportedDomainsNote>,
+struct GetEmailAttachments<'a> {
+    address: &'a EmailAddress,
+    limit: u8,
+    offset: u32,
 }
-#[derive(Debug, Deserialize, Clone)]
-struct SupportedDomainsNote {
-    #[serde(rename = "supportedDomains")]
-    supported_domains: Vec<String>,
+
+impl Endpoint for GetEmailAttachments<'_> {
+    const NAME: &'static str = "get_email_attachments";
+    const METHOD: Method = Method::Get;
+    type Result = Vec<Attachment>;
+
+    fn path(&self) -> String {
+        format!(
+            "/emails/{}/attachments?limit={}&offset={}",
+            self.address, self.limit, self.offset
+        )
+    }
 }
 
 impl Client {
@@ -23,46 +30,57 @@ impl Client {
     /// minimum offset is 0
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_email_attachments.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let emails = client.get_email_attachments(10, 0).await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn get_email_attachments(
         &self,
         limit: u8,
         offset: u32,
     ) -> Result<Vec<Attachment>, crate::ErrorType> {
-        let url = format!(
-            "{API_URL}/emails/{}/attachments?limit={limit}&offset={offset}",
-            self.email
-        );
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<GetEmailAttachmentsResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap())
-        } else {
-            Err(Box::new(EmailsError::from_get_email_attachments(response)))
-        }
+        self.call(GetEmailAttachments {
+            address: &self.email,
+            limit,
+            offset,
+        })
+        .await
     }
-}
-impl EmailsError {
-    fn from_get_email_attachments(response: GetEmailAttachmentsResponse) -> Self {
-        let error = response.error.unwrap();
 
-        match response.note {
-            Some(note) => Self::DomainError {
-                name: error.name,
-                message: error.description,
-                supported_domains: note.supported_domains,
-            },
-            None => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
+    /// Get every attachment for an email, fetching pages of 100 until the last one.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_email_attachments.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let attachments = client.get_all_email_attachments().await.unwrap();
+    /// # });
+    /// ```
+    pub async fn get_all_email_attachments(&self) -> Result<Vec<Attachment>, crate::ErrorType> {
+        let mut attachments = vec![];
+        loop {
+            let page = self
+                .get_email_attachments(PAGE_SIZE, attachments.len() as u32)
+                .await?;
+            let last = page.len() < PAGE_SIZE as usize;
+            attachments.extend(page);
+            if last {
+                return Ok(attachments);
+            }
         }
     }
 }
@@ -70,11 +88,14 @@ impl EmailsError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::{ApiResponse, SupportedDomainsNote};
+    use crate::types::errors::EmailsError;
 
     #[test]
     fn domain_error_conversion() {
-        let response = GetEmailAttachmentsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Attachment>> {
+            success: Some(false),
             error: Some(Error {
                 name: "DomainError".into(),
                 description: "Invalid domain".into(),
@@ -84,20 +105,21 @@ mod tests {
             }),
             result: None,
         };
-        let error = EmailsError::from_get_email_attachments(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::DomainError {
                 name: "DomainError".into(),
                 message: "Invalid domain".into(),
-                supported_domains: vec![]
+                supported_domains: vec![],
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = GetEmailAttachmentsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Attachment>> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
@@ -105,13 +127,34 @@ mod tests {
             note: None,
             result: None,
         };
-        let error = EmailsError::from_get_email_attachments(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
             }
         )
     }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<Vec<Attachment>> = fixture!("get_email_attachments/success.json");
+        assert_eq!(response.success, Some(true));
+        let attachments = response.result.unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename, "invoice.pdf");
+        assert_eq!(attachments[0].size, 48213);
+        assert_eq!(attachments[0].created_at.unix_timestamp(), 1_752_000_000);
+    }
+    #[test]
+    fn domain_error_fixture() {
+        let response: ApiResponse<Vec<Attachment>> =
+            fixture!("get_email_attachments/domain_error.json");
+        let error = response.into_error();
+        assert!(matches!(
+            error,
+            EmailsError::DomainError { ref supported_domains, .. } if supported_domains.len() == 2
+        ));
+    }
 }
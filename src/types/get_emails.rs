@@ -1,22 +1,31 @@
-use crate::client::API_URL;
-use crate::types::Error;
-use serde::Deserialize;
+use time::OffsetDateTime;
 
 use crate::{
     client::Client,
-    types::{Email, errors::EmailsError},
+    transport::Method,
+    types::{Email, address::EmailAddress, endpoint::Endpoint},
 };
-#[derive(Debug, Deserialize, Clone)]
-struct GetEmailsResponse {
-    success: bool,
-    result: Option<Vec<Email>>,
-    error: Option<Error>,
-    note: Option<SupportedDomainsNote>,
+
+/// Largest page the API serves.
+pub(super) const PAGE_SIZE: u8 = 100;
+
+struct GetEmails<'a> {
+    address: &'a EmailAddress,
+    limit: u8,
+    offset: u32,
 }
-#[derive(Debug, Deserialize, Clone)]
-struct SupportedDomainsNote {
-    #[serde(rename = "supportedDomains")]
-    supported_domains: Vec<String>,
+
+impl Endpoint for GetEmails<'_> {
+    const NAME: &'static str = "get_emails";
+    const METHOD: Method = Method::Get;
+    type Result = Vec<Email>;
+
+    fn path(&self) -> String {
+        format!(
+            "/emails/{}?limit={}&offset={}",
+            self.address, self.limit, self.offset
+        )
+    }
 }
 
 impl Client {
@@ -26,54 +35,93 @@ impl Client {
     /// minimum offset is 0
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_emails.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let emails = client.get_emails(10, 0).await.unwrap();
-    /// # };
+    /// # });
     /// ```
     pub async fn get_emails(&self, limit: u8, offset: u32) -> Result<Vec<Email>, crate::ErrorType> {
-        let url = format!(
-            "{API_URL}/emails/{}?limit={limit}&offset={offset}",
-            self.email
-        );
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<GetEmailsResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap())
-        } else {
-            Err(Box::new(EmailsError::from_get_emails(response)))
-        }
+        self.call(GetEmails {
+            address: &self.email,
+            limit,
+            offset,
+        })
+        .await
     }
-}
-impl EmailsError {
-    fn from_get_emails(response: GetEmailsResponse) -> Self {
-        let error = response.error.unwrap();
 
-        match response.note {
-            Some(note) => Self::DomainError {
-                name: error.name,
-                message: error.description,
-                supported_domains: note.supported_domains,
-            },
-            None => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
+    /// Get every message for an email, fetching pages of 100 until the last one.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_emails.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let emails = client.get_all_emails().await.unwrap();
+    /// # });
+    /// ```
+    pub async fn get_all_emails(&self) -> Result<Vec<Email>, crate::ErrorType> {
+        let mut emails = vec![];
+        loop {
+            let page = self.get_emails(PAGE_SIZE, emails.len() as u32).await?;
+            let last = page.len() < PAGE_SIZE as usize;
+            emails.extend(page);
+            if last {
+                return Ok(emails);
+            }
         }
     }
+
+    /// Get every message received at or after `since`, oldest first.
+    ///
+    /// ## Example
+    /// ```
+    /// use time::{Duration, OffsetDateTime};
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_emails.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let hour_ago = OffsetDateTime::now_utc() - Duration::hours(1);
+    /// let emails = client.get_emails_since(hour_ago).await.unwrap();
+    /// # });
+    /// ```
+    pub async fn get_emails_since(
+        &self,
+        since: OffsetDateTime,
+    ) -> Result<Vec<Email>, crate::ErrorType> {
+        let mut emails = self.get_all_emails().await?;
+        emails.retain(|email| email.received_at >= since);
+        emails.sort_by_key(|email| email.received_at);
+        Ok(emails)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::{ApiResponse, SupportedDomainsNote};
+    use crate::types::errors::EmailsError;
 
     #[test]
     fn domain_error_conversion() {
-        let response = GetEmailsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Email>> {
+            success: Some(false),
             error: Some(Error {
                 name: "DomainError".into(),
                 description: "Invalid domain".into(),
@@ -83,20 +131,21 @@ mod tests {
             }),
             result: None,
         };
-        let error = EmailsError::from_get_emails(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::DomainError {
                 name: "DomainError".into(),
                 message: "Invalid domain".into(),
-                supported_domains: vec![]
+                supported_domains: vec![],
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = GetEmailsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Email>> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
@@ -104,13 +153,53 @@ mod tests {
             note: None,
             result: None,
         };
-        let error = EmailsError::from_get_emails(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
             }
         )
     }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<Vec<Email>> = fixture!("get_emails/success.json");
+        assert_eq!(response.success, Some(true));
+        let emails = response.result.unwrap();
+        assert_eq!(emails.len(), 1);
+        let email = &emails[0];
+        assert_eq!(email.id, "k3u7m0c2x9");
+        assert_eq!(email.from_address.email(), "sender@example.com");
+        assert_eq!(email.from_address.display_name(), Some("Sender"));
+        assert_eq!(email.to_address.as_str(), "y@iusearch.lol");
+        assert_eq!(email.received_at.unix_timestamp(), 1_752_000_000);
+        assert_eq!(email.text_content.as_deref(), Some("Your code is 123456"));
+        assert!(email.has_attachments);
+        assert_eq!(email.attachment_count, 1);
+    }
+    #[test]
+    fn domain_error_fixture() {
+        let response: ApiResponse<Vec<Email>> = fixture!("get_emails/domain_error.json");
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::DomainError {
+                name: "ValidationError".into(),
+                message: "Unsupported domain".into(),
+                supported_domains: vec!["barid.site".into(), "iusearch.lol".into()],
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn unknown_fields() {
+        let json = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/get_emails/unknown_field.json"
+        ));
+        let response = serde_json::from_str::<ApiResponse<Vec<Email>>>(json);
+        assert_eq!(response.is_err(), cfg!(feature = "strict"));
+    }
 }
@@ -1,105 +1,126 @@
-use crate::client::API_URL;
-use crate::types::Error;
-use serde::Deserialize;
-
 use crate::{
     client::Client,
-    types::{Email, errors::EmailsError},
+    transport::Method,
+    types::{
+        Email,
+        endpoint::{Endpoint, require_id},
+        errors::EmailsError,
+    },
 };
-#[derive(Debug, Deserialize, Clone)]
-struct GetInboxResponse {
-    success: bool,
-    result: Option<Email>,
-    error: Option<Error>,
+
+struct GetInbox {
+    id: String,
+}
+
+impl Endpoint for GetInbox {
+    const NAME: &'static str = "get_inbox";
+    const METHOD: Method = Method::Get;
+    type Result = Email;
+
+    fn path(&self) -> String {
+        format!("/inbox/{}", self.id)
+    }
+    fn validate(&self) -> Result<(), EmailsError> {
+        require_id(&self.id, "Email not found")
+    }
 }
 
 impl Client {
     /// Get a specific message by its ID.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_inbox.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
-    /// let emails = client.get_inbox("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
-    /// # };
+    /// # let client = client.with_transport(vcr);
+    /// let emails = client.get_inbox("k3u7m0c2x9").await.unwrap();
+    /// # });
     /// ```
     pub async fn get_inbox<S>(&self, email_id: S) -> Result<Email, crate::ErrorType>
     where
         S: Into<String>,
     {
-        let email_id: String = email_id.into();
-        if email_id.is_empty() {
-            return Err(Box::new(EmailsError::NotFoundError {
-                name: "NotFound".to_owned(),
-                message: "Email not found".to_owned(),
-            }));
-        }
-        let url = format!("{API_URL}/inbox/{email_id}");
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<GetInboxResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap())
-        } else {
-            Err(Box::new(EmailsError::from_get_inbox(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_get_inbox(response: GetInboxResponse) -> Self {
-        let error = response.error.unwrap();
-        match error.name.as_str() {
-            "NotFound" => Self::NotFoundError {
-                name: error.name,
-                message: error.description,
-            },
-            _ => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
-        }
+        self.call(GetInbox {
+            id: email_id.into(),
+        })
+        .await
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
 
     #[test]
     fn notfound_error_conversion() {
-        let response = GetInboxResponse {
-            success: false,
+        let response = ApiResponse::<Email> {
+            success: Some(false),
             error: Some(Error {
                 name: "NotFound".into(),
                 description: "Inbox not found".into(),
             }),
             result: None,
+            note: None,
         };
-        let error = EmailsError::from_get_inbox(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::NotFoundError {
                 name: "NotFound".into(),
                 message: "Inbox not found".into(),
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = GetInboxResponse {
-            success: false,
+        let response = ApiResponse::<Email> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
             result: None,
+            note: None,
         };
-        let error = EmailsError::from_get_inbox(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
+            }
+        )
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<Email> = fixture!("get_inbox/success.json");
+        assert_eq!(response.success, Some(true));
+        let email = response.result.unwrap();
+        assert_eq!(email.subject, "Your code is 123456");
+        assert_eq!(
+            email.html_content.as_deref(),
+            Some("<p>Your code is <b>123456</b></p>")
+        );
+    }
+    #[test]
+    fn not_found_fixture() {
+        let response: ApiResponse<Email> = fixture!("get_inbox/not_found.json");
+        let error = response.into_error();
+        assert_eq!(
+            error,
+            EmailsError::NotFoundError {
+                name: "NotFound".into(),
+                message: "Inbox not found".into(),
+                status: None,
             }
         )
     }
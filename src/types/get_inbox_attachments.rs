@@ -1,103 +1,118 @@
-use crate::types::Error;
-use crate::{client::API_URL, types::Attachment};
-use serde::Deserialize;
+use crate::{
+    client::Client,
+    transport::Method,
+    types::{
+        Attachment,
+        endpoint::{Endpoint, require_id},
+        errors::EmailsError,
+    },
+};
 
-use crate::{client::Client, types::errors::EmailsError};
-#[derive(Debug, Deserialize, Clone)]
-struct GetAttachmentsResponse {
-    success: bool,
-    result: Option<Vec<Attachment>>,
-    error: Option<Error>,
+struct GetAttachments {
+    id: String,
+}
+
+impl Endpoint for GetAttachments {
+    const NAME: &'static str = "get_attachments";
+    const METHOD: Method = Method::Get;
+    type Result = Vec<Attachment>;
+
+    fn path(&self) -> String {
+        format!("/inbox/{}/attachments", self.id)
+    }
+    fn validate(&self) -> Result<(), EmailsError> {
+        require_id(&self.id, "Attachment not found")
+    }
 }
 
 impl Client {
     /// Get attachments for an inbox by its ID.
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/get_inbox_attachments.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
-    /// let emails = client.get_attachments("usm2sw0qfv9a5ku9z4xmh8og").await.unwrap();
-    /// # };
+    /// # let client = client.with_transport(vcr);
+    /// let emails = client.get_attachments("k3u7m0c2x9").await.unwrap();
+    /// # });
     /// ```
     pub async fn get_attachments<S>(&self, email_id: S) -> Result<Vec<Attachment>, crate::ErrorType>
     where
         S: Into<String>,
     {
-        let email_id: String = email_id.into();
-        if email_id.is_empty() {
-            return Err(Box::new(EmailsError::NotFoundError {
-                name: "NotFound".to_owned(),
-                message: "Attachment not found".to_owned(),
-            }));
-        }
-        let url = format!("{API_URL}/inbox/{email_id}/attachments");
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<GetAttachmentsResponse>().await?;
-        if response.success {
-            Ok(response.result.unwrap())
-        } else {
-            Err(Box::new(EmailsError::from_get_attachment(response)))
-        }
-    }
-}
-impl EmailsError {
-    fn from_get_attachment(response: GetAttachmentsResponse) -> Self {
-        let error = response.error.unwrap();
-        match error.name.as_str() {
-            "NotFound" => Self::NotFoundError {
-                name: error.name,
-                message: error.description,
-            },
-            _ => Self::ValidationError {
-                name: error.name,
-                message: error.description,
-            },
-        }
+        self.call(GetAttachments {
+            id: email_id.into(),
+        })
+        .await
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Error;
+    use crate::types::endpoint::ApiResponse;
 
     #[test]
     fn notfound_error_conversion() {
-        let response = GetAttachmentsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Attachment>> {
+            success: Some(false),
             error: Some(Error {
                 name: "NotFound".into(),
                 description: "Attachment not found".into(),
             }),
             result: None,
+            note: None,
         };
-        let error = EmailsError::from_get_attachment(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::NotFoundError {
                 name: "NotFound".into(),
                 message: "Attachment not found".into(),
+                status: None,
             }
         )
     }
     #[test]
     fn validation_error_conversion() {
-        let response = GetAttachmentsResponse {
-            success: false,
+        let response = ApiResponse::<Vec<Attachment>> {
+            success: Some(false),
             error: Some(Error {
                 name: "ValidationError".into(),
                 description: "Invalid input".into(),
             }),
             result: None,
+            note: None,
         };
-        let error = EmailsError::from_get_attachment(response);
+        let error = response.into_error();
         assert_eq!(
             error,
             EmailsError::ValidationError {
                 name: "ValidationError".into(),
                 message: "Invalid input".into(),
+                status: None,
             }
         )
     }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<Vec<Attachment>> = fixture!("get_inbox_attachments/success.json");
+        assert_eq!(response.success, Some(true));
+        let attachments = response.result.unwrap();
+        assert_eq!(attachments[0].id, "usm2sw0qfv9a5ku9z4xmh8og");
+        assert_eq!(attachments[0].content_type, "application/pdf");
+    }
+    #[test]
+    fn not_found_fixture() {
+        let response: ApiResponse<Vec<Attachment>> =
+            fixture!("get_inbox_attachments/not_found.json");
+        let error = response.into_error();
+        assert!(error.is_not_found());
+    }
 }
@@ -1,44 +1,258 @@
-use serde::Deserialize;
+use std::time::{Duration, Instant};
 
-use crate::client::{API_URL, Client};
+use futures_util::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use tokio::time::MissedTickBehavior;
 
-#[derive(Debug, Deserialize, Clone)]
-struct HealthResponse {
-    result: ServerHealth,
+use crate::{
+    client::Client, retention::MIN_INTERVAL, transport::Method, types::endpoint::Endpoint,
+};
+
+struct Health;
+
+impl Endpoint for Health {
+    const NAME: &'static str = "server_health";
+    const METHOD: Method = Method::Get;
+    type Result = ServerHealth;
+
+    fn path(&self) -> String {
+        "/health".into()
+    }
 }
-#[derive(Debug, Deserialize, Clone)]
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct ServerHealth {
     pub worker: ServerHealthStatus,
     pub database: ServerHealthStatus,
     pub kv: ServerHealthStatus,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// A [`ServerHealth`] report and how long it took to get, see [`Client::check_health`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct HealthCheck {
+    pub health: ServerHealth,
+    /// Round-trip time of the health request, measured by the client
+    pub latency: Duration,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(from = "String", into = "String")]
 pub enum ServerHealthStatus {
-    #[serde(rename = "connected")]
     Connected,
-    #[serde(rename = "disconnected")]
     Disconnected,
+    /// A status this version of the crate doesn't know about
+    Unknown(String),
+}
+impl From<String> for ServerHealthStatus {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "connected" => Self::Connected,
+            "disconnected" => Self::Disconnected,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<ServerHealthStatus> for String {
+    fn from(value: ServerHealthStatus) -> Self {
+        match value {
+            ServerHealthStatus::Connected => "connected".into(),
+            ServerHealthStatus::Disconnected => "disconnected".into(),
+            ServerHealthStatus::Unknown(status) => status,
+        }
+    }
+}
+
+/// Overall state of the service, as reported by [`Client::health_monitor`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HealthState {
+    /// Every component is connected
+    Healthy,
+    /// The service answered, but some component isn't connected
+    Degraded,
+    /// The health request itself failed
+    Unreachable,
+}
+
+/// A change of [`HealthState`], emitted by [`Client::health_monitor`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct HealthTransition {
+    /// The previous state, `None` for the first check
+    pub previous: Option<HealthState>,
+    pub current: HealthState,
+    /// The health check, when the service was reachable
+    pub health: Option<HealthCheck>,
+    /// Why the service was unreachable
+    pub error: Option<String>,
+}
+
+impl ServerHealth {
+    /// Whether every component is connected.
+    pub fn is_healthy(&self) -> bool {
+        [&self.worker, &self.database, &self.kv]
+            .iter()
+            .all(|status| **status == ServerHealthStatus::Connected)
+    }
+    fn state(&self) -> HealthState {
+        if self.is_healthy() {
+            HealthState::Healthy
+        } else {
+            HealthState::Degraded
+        }
+    }
 }
+
 impl Client {
     /// Check server health
     ///
     /// ## Example
-    /// ```no_run
+    /// ```
     /// use tmapi::Client;
     /// use tmapi::ServerHealthStatus;
     ///
-    /// # async {
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/health.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
     /// let status = client.server_health().await.unwrap();
     /// assert_eq!(status.database, ServerHealthStatus::Connected);
-    /// # };
+    /// # });
+    /// ```
+    ///
+    pub async fn server_health(&self) -> Result<ServerHealth, crate::ErrorType> {
+        self.call(Health).await
+    }
+
+    /// Check server health, measuring the round-trip time of the request
+    ///
+    /// ## Example
     /// ```
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/health.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let check = client.check_health().await.unwrap();
+    /// println!("healthy: {} in {:?}", check.health.is_healthy(), check.latency);
+    /// # });
+    /// ```
+    pub async fn check_health(&self) -> Result<HealthCheck, crate::ErrorType> {
+        let start = Instant::now();
+        let health = self.server_health().await?;
+        Ok(HealthCheck {
+            health,
+            latency: start.elapsed(),
+        })
+    }
+
+    /// Check server health every `interval`, yielding whenever the [`HealthState`] changes.
+    /// The first check is always yielded. Intervals below a second are raised to one second.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use futures_util::StreamExt;
+    /// use tmapi::Client;
     ///
-    pub async fn server_health(self) -> Result<ServerHealth, crate::ErrorType> {
-        let url = format!("{API_URL}/health");
-        let response = self.client.get(url).send().await?;
-        let response = response.json::<HealthResponse>().await?;
-        Ok(response.result)
+    /// # async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// let mut transitions = Box::pin(client.health_monitor(Duration::from_secs(30)));
+    /// while let Some(transition) = transitions.next().await {
+    ///     println!("{:?} -> {:?}", transition.previous, transition.current);
+    /// }
+    /// # };
+    /// ```
+    pub fn health_monitor(&self, interval: Duration) -> impl Stream<Item = HealthTransition> {
+        let client = self.clone();
+        let interval = interval.max(MIN_INTERVAL);
+        stream::unfold(
+            (client, None, None),
+            move |(client, ticker, previous)| async move {
+                let mut ticker = ticker.unwrap_or_else(|| {
+                    let mut ticker = tokio::time::interval(interval);
+                    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    ticker
+                });
+                loop {
+                    ticker.tick().await;
+                    let transition = match client.check_health().await {
+                        Ok(check) => HealthTransition {
+                            previous,
+                            current: check.health.state(),
+                            health: Some(check),
+                            error: None,
+                        },
+                        Err(error) => HealthTransition {
+                            previous,
+                            current: HealthState::Unreachable,
+                            health: None,
+                            error: Some(error.to_string()),
+                        },
+                    };
+                    if previous != Some(transition.current) {
+                        let current = Some(transition.current);
+                        return Some((transition, (client, Some(ticker), current)));
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{endpoint::ApiResponse, fixtures::FixtureTransport};
+    use futures_util::StreamExt;
+
+    #[test]
+    fn unknown_status() {
+        let response: ApiResponse<ServerHealth> = serde_json::from_str(
+            r#"{"result":{"worker":"connected","database":"disconnected","kv":"degraded"}}"#,
+        )
+        .unwrap();
+        let health = response.into_result().unwrap();
+        assert_eq!(health.worker, ServerHealthStatus::Connected);
+        assert_eq!(health.database, ServerHealthStatus::Disconnected);
+        assert_eq!(health.kv, ServerHealthStatus::Unknown("degraded".into()));
+        assert_eq!(health.state(), HealthState::Degraded);
+    }
+    #[test]
+    fn status_round_trip() {
+        let statuses = vec![
+            ServerHealthStatus::Connected,
+            ServerHealthStatus::Disconnected,
+            ServerHealthStatus::Unknown("degraded".into()),
+        ];
+        let json = serde_json::to_string(&statuses).unwrap();
+        assert_eq!(json, r#"["connected","disconnected","degraded"]"#);
+        let decoded: Vec<ServerHealthStatus> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, statuses);
+    }
+    #[test]
+    fn success_fixture() {
+        let response: ApiResponse<ServerHealth> = fixture!("health/success.json");
+        let health = response.into_result().unwrap();
+        assert!(health.is_healthy());
+    }
+    #[tokio::test]
+    async fn zero_interval() {
+        let client =
+            Client::new("y@iusearch.lol")
+                .unwrap()
+                .with_transport(FixtureTransport::new().route(
+                    Method::Get,
+                    "/health",
+                    "health/success.json",
+                ));
+        let mut transitions = Box::pin(client.health_monitor(Duration::ZERO));
+        let transition = transitions.next().await.unwrap();
+        assert_eq!(transition.current, HealthState::Healthy);
     }
 }
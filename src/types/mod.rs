@@ -1,7 +1,33 @@
+use std::hash::{Hash, Hasher};
+
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use address::EmailAddress;
+
+/// Decode a sample API response from `tests/fixtures`.
+#[cfg(test)]
+macro_rules! fixture {
+    ($path:literal) => {
+        serde_json::from_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/",
+            $path
+        )))
+        .unwrap()
+    };
+}
 
+pub(super) mod address;
+mod endpoint;
 pub(super) mod errors;
 pub(super) mod health;
+pub(super) mod readiness;
+pub(super) mod stats;
+pub(super) mod timestamp;
+
+#[cfg(test)]
+pub(super) mod fixtures;
 
 mod count_emails;
 mod delete_attachment;
@@ -14,18 +40,21 @@ mod get_emails;
 mod get_inbox;
 mod get_inbox_attachments;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A received message. Equality compares every field, hashing only uses the ID.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct Email {
     /// Message's ID
     pub id: String,
     /// Sender's address
-    pub from_address: String,
+    pub from_address: EmailAddress,
     /// Receiver's address
-    pub to_address: String,
+    pub to_address: EmailAddress,
     /// Message subject
     pub subject: String,
     /// Message received date
-    pub received_at: i64,
+    #[serde(with = "timestamp")]
+    pub received_at: OffsetDateTime,
     /// Message content formatted as html
     pub html_content: Option<String>,
     /// Message content formatted as text
@@ -36,12 +65,15 @@ pub struct Email {
     pub attachment_count: u32,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// An attachment's metadata. Equality compares every field, hashing only uses the ID.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct Attachment {
     /// The MIME type of the attachment.
     pub content_type: String,
-    /// The timestamp when the attachment was created (Unix epoch).
-    pub created_at: i64,
+    /// The timestamp when the attachment was created.
+    #[serde(with = "timestamp")]
+    pub created_at: OffsetDateTime,
     /// The original filename of the attachment.
     pub filename: String,
     /// The unique identifier for the attachment.
@@ -50,7 +82,19 @@ pub struct Attachment {
     pub size: u64,
 }
 
+impl Hash for Email {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl Hash for Attachment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 struct Error {
     /// Error name
     name: String,
@@ -58,3 +102,28 @@ struct Error {
     #[serde(rename = "message")]
     description: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const EMAIL: &str = r#"{"id":"abc","from_address":"noreply@example.com","to_address":"y@iusearch.lol","subject":"Hi","received_at":1752000000,"html_content":null,"text_content":"Hi","has_attachments":false,"attachment_count":0}"#;
+
+    #[test]
+    fn email_json_round_trip() {
+        let email: Email = serde_json::from_str(EMAIL).unwrap();
+        assert_eq!(serde_json::to_string(&email).unwrap(), EMAIL);
+    }
+    #[test]
+    fn email_hash_uses_id() {
+        let email: Email = serde_json::from_str(EMAIL).unwrap();
+        let mut edited = email.clone();
+        edited.subject = "Edited".into();
+        assert_ne!(email, edited);
+
+        let set: HashSet<Email> = [email.clone(), email].into();
+        assert_eq!(set.len(), 1);
+    }
+}
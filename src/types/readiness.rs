@@ -0,0 +1,175 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::Client,
+    types::health::{HealthCheck, ServerHealth},
+};
+
+/// The outcome of [`Client::ensure_ready`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ReadinessReport {
+    /// The health report, when the service was reachable
+    pub health: Option<ServerHealth>,
+    /// The supported domains, empty when they couldn't be fetched
+    pub domains: Vec<String>,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+/// A single check of a [`ReadinessReport`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ReadinessCheck {
+    /// What was checked: `health`, `domains` or `address`
+    pub name: String,
+    pub passed: bool,
+    /// What was found, or why the check failed
+    pub reason: String,
+}
+
+impl ReadinessReport {
+    /// Whether every check passed.
+    pub fn is_ready(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &ReadinessCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+impl Display for ReadinessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let mark = if check.passed { "ok" } else { "FAIL" };
+            writeln!(f, "[{mark:>4}] {}: {}", check.name, check.reason)?;
+        }
+        if self.is_ready() {
+            write!(f, "ready")
+        } else {
+            write!(f, "not ready")
+        }
+    }
+}
+
+impl Client {
+    /// Check that the service is healthy and that the client's address uses a supported domain.
+    /// Failures are reported in the returned [`ReadinessReport`], this never errors.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/readiness.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let report = client.ensure_ready().await;
+    /// assert!(report.is_ready(), "{report}");
+    /// # });
+    /// ```
+    pub async fn ensure_ready(&self) -> ReadinessReport {
+        let (health, domains) = tokio::join!(self.check_health(), self.get_domains());
+        let mut checks = vec![];
+
+        let health = match health {
+            Ok(HealthCheck { health, latency }) => {
+                let reason = format!(
+                    "worker {:?}, database {:?}, kv {:?} in {}ms",
+                    health.worker,
+                    health.database,
+                    health.kv,
+                    latency.as_millis()
+                );
+                checks.push(ReadinessCheck {
+                    name: "health".into(),
+                    passed: health.is_healthy(),
+                    reason,
+                });
+                Some(health)
+            }
+            Err(error) => {
+                checks.push(ReadinessCheck {
+                    name: "health".into(),
+                    passed: false,
+                    reason: format!("health check failed: {error}"),
+                });
+                None
+            }
+        };
+
+        let domains = match domains {
+            Ok(domains) => {
+                checks.push(ReadinessCheck {
+                    name: "domains".into(),
+                    passed: !domains.is_empty(),
+                    reason: format!("{} supported domains", domains.len()),
+                });
+                domains
+            }
+            Err(error) => {
+                checks.push(ReadinessCheck {
+                    name: "domains".into(),
+                    passed: false,
+                    reason: format!("listing domains failed: {error}"),
+                });
+                vec![]
+            }
+        };
+
+        let domain = self.email.domain();
+        let supported = domains.iter().any(|d| d.eq_ignore_ascii_case(domain));
+        checks.push(ReadinessCheck {
+            name: "address".into(),
+            passed: supported,
+            reason: if supported {
+                format!("{domain} is supported")
+            } else if domains.is_empty() {
+                format!("can't check {domain} without the domain list")
+            } else {
+                format!("{domain} is not a supported domain")
+            },
+        });
+
+        ReadinessReport {
+            health,
+            domains,
+            checks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_display() {
+        let report = ReadinessReport {
+            health: None,
+            domains: vec![],
+            checks: vec![
+                ReadinessCheck {
+                    name: "domains".into(),
+                    passed: true,
+                    reason: "1 supported domains".into(),
+                },
+                ReadinessCheck {
+                    name: "address".into(),
+                    passed: false,
+                    reason: "example.com is not a supported domain".into(),
+                },
+            ],
+        };
+        assert!(!report.is_ready());
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "[  ok] domains: 1 supported domains\n\
+             [FAIL] address: example.com is not a supported domain\n\
+             not ready"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    client::Client,
+    types::{Attachment, Email, timestamp},
+};
+
+/// Histogram bucket widths, in seconds, from which [`InboxStats::arrivals`] picks the
+/// narrowest that needs at most [`MAX_BUCKETS`] buckets. Longer spans use a multiple of
+/// the widest.
+const BUCKET_WIDTHS: [u64; 7] = [
+    60,
+    5 * 60,
+    15 * 60,
+    60 * 60,
+    6 * 60 * 60,
+    24 * 60 * 60,
+    7 * 24 * 60 * 60,
+];
+const MAX_BUCKETS: u64 = 48;
+
+/// A summary of an inbox, see [`Client::stats`].
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct InboxStats {
+    pub messages: usize,
+    /// Messages by sender address, without display names
+    pub by_sender: BTreeMap<String, usize>,
+    /// Messages by sender domain
+    pub by_domain: BTreeMap<String, usize>,
+    /// Attachments by content type
+    pub attachments: BTreeMap<String, AttachmentStats>,
+    /// Messages received in consecutive time windows, from the oldest message to the newest
+    pub arrivals: Vec<ArrivalBucket>,
+    #[serde(default, with = "timestamp::option")]
+    pub oldest: Option<OffsetDateTime>,
+    #[serde(default, with = "timestamp::option")]
+    pub newest: Option<OffsetDateTime>,
+}
+
+/// Attachments of one content type.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AttachmentStats {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// Messages received from `start` until `start + width`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct ArrivalBucket {
+    #[serde(with = "timestamp")]
+    pub start: OffsetDateTime,
+    pub width: Duration,
+    pub count: usize,
+}
+
+impl InboxStats {
+    /// Summarize `emails` and `attachments`.
+    pub fn new(emails: &[Email], attachments: &[Attachment]) -> Self {
+        let mut stats = InboxStats {
+            messages: emails.len(),
+            oldest: emails.iter().map(|email| email.received_at).min(),
+            newest: emails.iter().map(|email| email.received_at).max(),
+            ..Self::default()
+        };
+        for email in emails {
+            let sender = email.from_address.email().to_lowercase();
+            let domain = email.from_address.domain().to_lowercase();
+            *stats.by_sender.entry(sender).or_default() += 1;
+            *stats.by_domain.entry(domain).or_default() += 1;
+        }
+        for attachment in attachments {
+            let content_type = attachment.content_type.to_lowercase();
+            let entry = stats.attachments.entry(content_type).or_default();
+            entry.count += 1;
+            entry.bytes += attachment.size;
+        }
+        if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+            let (first, last) = (oldest.unix_timestamp(), newest.unix_timestamp());
+            let width = bucket_width(first, last);
+            let start = first - first.rem_euclid(width);
+            let mut counts = vec![0; ((last - start) / width + 1) as usize];
+            for email in emails {
+                counts[((email.received_at.unix_timestamp() - start) / width) as usize] += 1;
+            }
+            stats.arrivals = counts
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, count)| {
+                    Some(ArrivalBucket {
+                        start: timestamp::from_unix(start + index as i64 * width).ok()?,
+                        width: Duration::from_secs(width as u64),
+                        count,
+                    })
+                })
+                .collect();
+        }
+        stats
+    }
+
+    /// How many attachments there are.
+    pub fn attachment_count(&self) -> usize {
+        self.attachments.values().map(|stats| stats.count).sum()
+    }
+
+    /// The size of every attachment, in bytes.
+    pub fn attachment_bytes(&self) -> u64 {
+        self.attachments.values().map(|stats| stats.bytes).sum()
+    }
+}
+
+impl Display for InboxStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} messages", self.messages)?;
+        if let (Some(oldest), Some(newest)) = (self.oldest, self.newest) {
+            write!(f, ", from {} to {}", Minute(oldest), Minute(newest))?;
+        }
+        writeln!(f)?;
+        for (title, counts) in [("senders", &self.by_sender), ("domains", &self.by_domain)] {
+            if !counts.is_empty() {
+                writeln!(f, "\n{title}:")?;
+            }
+            let mut counts: Vec<_> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (name, count) in counts {
+                writeln!(f, "{count:>6}  {name}")?;
+            }
+        }
+        writeln!(
+            f,
+            "\n{} attachments, {} bytes",
+            self.attachment_count(),
+            self.attachment_bytes()
+        )?;
+        for (content_type, stats) in &self.attachments {
+            writeln!(
+                f,
+                "{:>6}  {content_type} ({} bytes)",
+                stats.count, stats.bytes
+            )?;
+        }
+        if let Some(bucket) = self.arrivals.first() {
+            writeln!(f, "\narrivals per {}s:", bucket.width.as_secs())?;
+        }
+        let max = self
+            .arrivals
+            .iter()
+            .map(|bucket| bucket.count)
+            .max()
+            .unwrap_or(1);
+        for bucket in &self.arrivals {
+            let bar = "#".repeat((bucket.count * 40).div_ceil(max.max(1)));
+            writeln!(f, "{}  {:>4} {bar}", Minute(bucket.start), bucket.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The narrowest bucket width, in seconds, that covers `first..=last` in at most
+/// [`MAX_BUCKETS`] buckets aligned to the width.
+fn bucket_width(first: i64, last: i64) -> i64 {
+    let buckets = |width: i64| (last - (first - first.rem_euclid(width))) / width + 1;
+    let widest = BUCKET_WIDTHS[BUCKET_WIDTHS.len() - 1];
+    BUCKET_WIDTHS
+        .into_iter()
+        .chain((2..).map(|multiple| multiple * widest))
+        .map(|width| width as i64)
+        .find(|&width| buckets(width) <= MAX_BUCKETS as i64)
+        .unwrap_or(widest as i64)
+}
+
+/// `YYYY-MM-DD HH:MM UTC`, the `time` crate only formats with its `formatting` feature.
+struct Minute(OffsetDateTime);
+
+impl Display for Minute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.0.to_offset(time::UtcOffset::UTC);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+            time.year(),
+            time.month() as u8,
+            time.day(),
+            time.hour(),
+            time.minute()
+        )
+    }
+}
+
+impl Client {
+    /// Summarize the inbox: who sends what, attachments by content type, and when
+    /// messages arrive. Fetches every message and attachment.
+    ///
+    /// ## Example
+    /// ```
+    /// use tmapi::Client;
+    ///
+    /// # use tmapi::vcr::{Matching, VcrTransport};
+    /// # let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/stats.json");
+    /// # let vcr = VcrTransport::replay(cassette).unwrap().matching(Matching::Lenient);
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let client = Client::new("y@iusearch.lol").unwrap();
+    /// # let client = client.with_transport(vcr);
+    /// let stats = client.stats().await.unwrap();
+    /// println!("{stats}");
+    /// # });
+    /// ```
+    pub async fn stats(&self) -> Result<InboxStats, crate::ErrorType> {
+        let emails = self.get_all_emails().await?;
+        let attachments = self.get_all_email_attachments().await?;
+        Ok(InboxStats::new(&emails, &attachments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::endpoint::ApiResponse;
+
+    #[test]
+    fn bucket_count_is_capped() {
+        let year = 365 * 24 * 60 * 60;
+        for (first, last) in [(0, 47 * 60), (1_752_000_000, 1_752_000_000 + 10 * year)] {
+            let width = bucket_width(first, last);
+            let start = first - first.rem_euclid(width);
+            assert!((last - start) / width < MAX_BUCKETS as i64);
+        }
+        assert_eq!(bucket_width(0, 47 * 60), 60);
+        assert_eq!(bucket_width(0, 48 * 60), 5 * 60);
+    }
+    #[test]
+    fn summary() {
+        let emails: ApiResponse<Vec<Email>> = fixture!("get_emails/success.json");
+        let emails = emails.into_result().unwrap();
+        let attachments: ApiResponse<Vec<Attachment>> =
+            fixture!("get_email_attachments/success.json");
+        let attachments = attachments.into_result().unwrap();
+        let mut later = emails[0].clone();
+        later.from_address = "news@Example.com".parse().unwrap();
+        later.received_at += time::Duration::minutes(90);
+        let stats = InboxStats::new(&[emails[0].clone(), later], &attachments);
+
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.by_domain["example.com"], 2);
+        assert_eq!(stats.by_sender["news@example.com"], 1);
+        assert_eq!(stats.attachment_count(), 1);
+        assert_eq!(stats.attachment_bytes(), 48213);
+        let counts: Vec<usize> = stats.arrivals.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts.len(), 19);
+        assert_eq!((counts[0], counts[18]), (1, 1));
+        assert_eq!(counts.iter().sum::<usize>(), 2);
+        assert_eq!(stats.arrivals[0].width, Duration::from_secs(5 * 60));
+        assert!(
+            stats
+                .to_string()
+                .starts_with("2 messages, from 2025-07-08 18:40 UTC to 2025-07-08 20:10 UTC\n")
+        );
+    }
+    #[test]
+    fn missing_timestamps() {
+        let stats: InboxStats = serde_json::from_str(
+            r#"{"messages":0,"by_sender":{},"by_domain":{},"attachments":{},"arrivals":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(stats, InboxStats::default());
+    }
+}
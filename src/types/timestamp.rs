@@ -0,0 +1,92 @@
+//! Serde helpers for the Unix timestamps used by the API, for `#[serde(with = "timestamp")]`.
+//!
+//! Timestamps are sent as whole seconds. Values too large to be seconds (after the year 5000)
+//! are read as milliseconds, so both encodings decode to the same instant.
+//!
+//! Serializing always writes whole seconds, so the round trip is lossy: a millisecond
+//! timestamp comes back as seconds, without its sub-second part.
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use time::OffsetDateTime;
+
+/// Anything larger is taken as milliseconds, `100_000_000_000` seconds is in the year 5138.
+const MAX_SECONDS: i64 = 100_000_000_000;
+
+pub(crate) fn serialize<S>(value: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_i64(value.unix_timestamp())
+}
+
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = i64::deserialize(deserializer)?;
+    from_unix(timestamp).map_err(D::Error::custom)
+}
+
+pub(crate) fn from_unix(timestamp: i64) -> Result<OffsetDateTime, time::error::ComponentRange> {
+    if timestamp.unsigned_abs() >= MAX_SECONDS as u64 {
+        // Saturates out of range, which is an error.
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(timestamp).saturating_mul(1_000_000))
+    } else {
+        OffsetDateTime::from_unix_timestamp(timestamp)
+    }
+}
+
+/// The same for optional timestamps, for `#[serde(with = "timestamp::option")]`.
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<S>(
+        value: &Option<OffsetDateTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        value
+            .map(OffsetDateTime::unix_timestamp)
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<i64>::deserialize(deserializer)?
+            .map(from_unix)
+            .transpose()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds_and_milliseconds() {
+        let seconds = from_unix(1_752_000_000).unwrap();
+        let milliseconds = from_unix(1_752_000_000_000).unwrap();
+        assert_eq!(seconds, milliseconds);
+        assert_eq!(seconds.year(), 2025);
+    }
+    #[test]
+    fn out_of_range() {
+        assert!(from_unix(i64::MIN).is_err());
+        assert!(from_unix(i64::MAX).is_err());
+        let error = serde_json::from_str::<Wrapper>(&format!("{}", i64::MIN));
+        assert!(error.is_err());
+    }
+    #[test]
+    fn lossy_round_trip() {
+        let decoded: Wrapper = serde_json::from_str("1752000000123").unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), "1752000000");
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct Wrapper(#[serde(with = "super")] OffsetDateTime);
+}
@@ -0,0 +1,480 @@
+//! Record API interactions into a cassette and replay them offline.
+//!
+//! A [`VcrTransport`] either records: sends requests through another [`Transport`] and writes
+//! every request and response to a JSON cassette file, or replays: answers requests from a
+//! cassette without touching the network.
+//!
+//! ## Example
+//! ```
+//! use tmapi::Client;
+//! use tmapi::vcr::{Cassette, VcrTransport};
+//!
+//! let cassette = Cassette::from_json(r#"{
+//!     "interactions": [{
+//!         "request": {"method": "GET", "url": "https://api.driftz.net/domains"},
+//!         "response": {"status": 200, "body": "{\"success\":true,\"result\":[\"barid.site\"]}"}
+//!     }]
+//! }"#).unwrap();
+//! let client = Client::new("y@barid.site")
+//!     .unwrap()
+//!     .with_transport(VcrTransport::from_cassette(cassette));
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let domains = client.get_domains().await.unwrap();
+//! assert_eq!(domains, ["barid.site"]);
+//! # });
+//! ```
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::transport::{HttpRequest, HttpResponse, Method, ReqwestTransport, Transport};
+
+/// Recorded request and response pairs, in the order they were made.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Body::is_empty")]
+    pub body: Body,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Body::is_empty")]
+    pub body: Body,
+}
+
+/// A body as text, or base64 when it isn't UTF-8.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Body {
+    Text(String),
+    Base64 {
+        base64: String,
+    },
+    #[default]
+    #[serde(skip)]
+    Empty,
+}
+
+impl Body {
+    fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::Empty;
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(error) => Self::Base64 {
+                base64: BASE64_STANDARD.encode(error.as_bytes()),
+            },
+        }
+    }
+    fn into_bytes(self) -> Result<Vec<u8>, base64::DecodeError> {
+        match self {
+            Self::Text(text) => Ok(text.into_bytes()),
+            Self::Base64 { base64 } => BASE64_STANDARD.decode(base64),
+            Self::Empty => Ok(vec![]),
+        }
+    }
+}
+
+impl Cassette {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(Self::from_json(&json)?)
+    }
+    /// Write the cassette as pretty-printed JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+/// How a request is matched against the cassette when replaying.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Matching {
+    /// Requests must be made in the recorded order, with the same method, URL and body.
+    #[default]
+    Strict,
+    /// Any unused interaction with the same method and URL path, ignoring the query string.
+    /// Once every match was used, the last one is replayed again.
+    Lenient,
+}
+
+/// What to hide in recorded interactions.
+///
+/// Replacements are applied to requests before they are matched too, so a cassette recorded
+/// with a redacted address can be replayed by a client using the real one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Redaction {
+    replacements: Vec<(String, String)>,
+    fields: Vec<String>,
+}
+
+impl Redaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Replace every occurrence of `address` in URLs, headers and bodies with `replacement`.
+    pub fn address(mut self, address: impl Into<String>, replacement: impl Into<String>) -> Self {
+        self.replacements.push((address.into(), replacement.into()));
+        self
+    }
+    /// Replace the value of every JSON string field called `name` in response bodies with
+    /// `"[REDACTED]"`.
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+    /// Redact the subject and content of messages.
+    pub fn message_contents(self) -> Self {
+        self.field("subject")
+            .field("html_content")
+            .field("text_content")
+    }
+
+    fn text(&self, text: &str) -> String {
+        self.replacements
+            .iter()
+            .fold(text.to_owned(), |text, (from, to)| text.replace(from, to))
+    }
+    fn body(&self, body: Body) -> Body {
+        let Body::Text(text) = body else {
+            return body;
+        };
+        let text = self.text(&text);
+        if self.fields.is_empty() {
+            return Body::Text(text);
+        }
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(mut value) => {
+                self.value(&mut value);
+                Body::Text(value.to_string())
+            }
+            Err(_) => Body::Text(text),
+        }
+    }
+    fn value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if value.is_string() && self.fields.contains(key) {
+                        *value = "[REDACTED]".into();
+                    } else {
+                        self.value(value);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.value(v)),
+            _ => {}
+        }
+    }
+    fn request(&self, request: HttpRequest) -> RecordedRequest {
+        RecordedRequest {
+            method: request.method,
+            url: self.text(&request.url),
+            headers: self.headers(request.headers),
+            body: self.body(Body::from_bytes(request.body)),
+        }
+    }
+    fn response(&self, response: HttpResponse) -> RecordedResponse {
+        RecordedResponse {
+            status: response.status,
+            headers: self.headers(response.headers),
+            body: self.body(Body::from_bytes(response.body)),
+        }
+    }
+    fn headers(&self, headers: Vec<(String, String)>) -> Vec<(String, String)> {
+        headers
+            .into_iter()
+            .map(|(name, value)| (name, self.text(&value)))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record {
+        inner: Arc<dyn Transport>,
+        path: PathBuf,
+    },
+    Replay {
+        matching: Matching,
+        used: Vec<bool>,
+    },
+}
+
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    cassette: Cassette,
+}
+
+/// A [`Transport`] that records to, or replays from, a [`Cassette`].
+#[derive(Debug)]
+pub struct VcrTransport {
+    state: Mutex<State>,
+    redaction: Redaction,
+}
+
+impl VcrTransport {
+    /// Record through the default [`ReqwestTransport`], rewriting `path` after every response.
+    pub fn record<P: Into<PathBuf>>(path: P) -> Self {
+        Self::record_with(path, ReqwestTransport::new())
+    }
+    /// Record through `inner`, rewriting `path` after every response.
+    pub fn record_with<P, T>(path: P, inner: T) -> Self
+    where
+        P: Into<PathBuf>,
+        T: Transport + 'static,
+    {
+        Self::new(
+            Mode::Record {
+                inner: Arc::new(inner),
+                path: path.into(),
+            },
+            Cassette::default(),
+        )
+    }
+    /// Replay the cassette stored at `path`.
+    pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_cassette(Cassette::load(path)?))
+    }
+    /// Replay `cassette`.
+    pub fn from_cassette(cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        Self::new(
+            Mode::Replay {
+                matching: Matching::Strict,
+                used,
+            },
+            cassette,
+        )
+    }
+    fn new(mode: Mode, cassette: Cassette) -> Self {
+        Self {
+            state: Mutex::new(State { mode, cassette }),
+            redaction: Redaction::default(),
+        }
+    }
+
+    /// How to match requests when replaying, [`Matching::Strict`] by default.
+    pub fn matching(self, matching: Matching) -> Self {
+        if let Mode::Replay { matching: m, .. } = &mut self.state.lock().unwrap().mode {
+            *m = matching;
+        }
+        self
+    }
+    /// What to hide in the cassette, nothing by default.
+    pub fn redact(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+    /// The interactions recorded or loaded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    fn replay_request(&self, request: HttpRequest) -> Result<HttpResponse, crate::ErrorType> {
+        let request = self.redaction.request(request);
+        let mut state = self.state.lock().unwrap();
+        let State { mode, cassette } = &mut *state;
+        let Mode::Replay { matching, used } = mode else {
+            unreachable!("only called when replaying");
+        };
+        let interactions = &cassette.interactions;
+        let found = match matching {
+            Matching::Strict => used
+                .iter()
+                .position(|used| !used)
+                .filter(|&i| interactions[i].request == request),
+            Matching::Lenient => {
+                let candidates: Vec<usize> = (0..interactions.len())
+                    .filter(|&i| lenient_match(&interactions[i].request, &request))
+                    .collect();
+                candidates
+                    .iter()
+                    .copied()
+                    .find(|&i| !used[i])
+                    .or(candidates.last().copied())
+            }
+        };
+        let Some(i) = found else {
+            return Err(format!(
+                "no recorded interaction matches {} {}",
+                request.method, request.url
+            )
+            .into());
+        };
+        used[i] = true;
+        let response = interactions[i].response.clone();
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body.into_bytes()?,
+        })
+    }
+
+    fn record_interaction(
+        &self,
+        request: RecordedRequest,
+        response: HttpResponse,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let State { mode, cassette } = &mut *state;
+        cassette.interactions.push(Interaction {
+            request,
+            response: self.redaction.response(response),
+        });
+        match mode {
+            Mode::Record { path, .. } => cassette.save(path),
+            Mode::Replay { .. } => Ok(()),
+        }
+    }
+}
+
+impl Transport for VcrTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, crate::ErrorType>> {
+        Box::pin(async move {
+            let inner = match &self.state.lock().unwrap().mode {
+                Mode::Record { inner, .. } => Some(inner.clone()),
+                Mode::Replay { .. } => None,
+            };
+            let Some(inner) = inner else {
+                return self.replay_request(request);
+            };
+            let recorded = self.redaction.request(request.clone());
+            let response = inner.send(request).await?;
+            self.record_interaction(recorded, response.clone())?;
+            Ok(response)
+        })
+    }
+}
+
+fn lenient_match(recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+    let path = |url: &str| url.split('?').next().unwrap_or_default().to_owned();
+    recorded.method == request.method && path(&recorded.url) == path(&request.url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Transport for Echo {
+        fn send(
+            &self,
+            request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, crate::ErrorType>> {
+            Box::pin(async move {
+                let body = format!(r#"{{"url":"{}","subject":"secret"}}"#, request.url);
+                Ok(HttpResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: body.into_bytes(),
+                })
+            })
+        }
+    }
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tmapi-vcr-{name}-{}.json", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn record_then_replay_redacted() {
+        let path = path("redacted");
+        let redaction = Redaction::new()
+            .address("y@iusearch.lol", "user@example.com")
+            .message_contents();
+        let recorder = VcrTransport::record_with(&path, Echo).redact(redaction.clone());
+        let request = HttpRequest::get("https://api.driftz.net/emails/y@iusearch.lol");
+        recorder.send(request.clone()).await.unwrap();
+
+        let cassette = Cassette::load(&path).unwrap();
+        let recorded = &cassette.interactions[0];
+        assert_eq!(
+            recorded.request.url,
+            "https://api.driftz.net/emails/user@example.com"
+        );
+        assert_eq!(
+            recorded.response.body,
+            Body::Text(
+                r#"{"subject":"[REDACTED]","url":"https://api.driftz.net/emails/user@example.com"}"#
+                    .into()
+            )
+        );
+
+        let replayer = VcrTransport::replay(&path).unwrap().redact(redaction);
+        let response = replayer.send(request.clone()).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert!(replayer.send(request).await.is_err());
+        fs::remove_file(path).unwrap();
+    }
+    #[tokio::test]
+    async fn lenient_matching() {
+        let cassette = Cassette {
+            interactions: vec![Interaction {
+                request: RecordedRequest {
+                    method: Method::Get,
+                    url: "https://api.driftz.net/emails/a@barid.site?limit=10".into(),
+                    headers: vec![],
+                    body: Body::Empty,
+                },
+                response: RecordedResponse {
+                    status: 200,
+                    headers: vec![],
+                    body: Body::Text("[]".into()),
+                },
+            }],
+        };
+        let request = HttpRequest::get("https://api.driftz.net/emails/a@barid.site?limit=20");
+        let strict = VcrTransport::from_cassette(cassette.clone());
+        assert!(strict.send(request.clone()).await.is_err());
+        let lenient = VcrTransport::from_cassette(cassette).matching(Matching::Lenient);
+        assert!(lenient.send(request.clone()).await.is_ok());
+        assert!(lenient.send(request).await.is_ok());
+    }
+    #[test]
+    fn binary_body() {
+        let body = Body::from_bytes(vec![0xff, 0x00]);
+        assert_eq!(
+            body,
+            Body::Base64 {
+                base64: "/wA=".into()
+            }
+        );
+        assert_eq!(body.into_bytes().unwrap(), vec![0xff, 0x00]);
+    }
+}